@@ -11,5 +11,8 @@ fuzz_target!(|data: &[u8]| {
     let Ok(syntax_tree) = syn::parse_file(string) else {
         return;
     };
-    let _ = prettyplease::unparse(&syntax_tree);
+    let config = prettyplease::Config::default();
+    if let Err(error) = prettyplease::verify_semantics(&syntax_tree, &config) {
+        panic!("{}", error);
+    }
 });
@@ -1,11 +1,18 @@
 // Adapted from https://github.com/rust-lang/rust/blob/1.57.0/compiler/rustc_ast_pretty/src/pp.rs.
 // See "Algorithm notes" in the crate-level rustdoc.
 
+use crate::comment::Comment;
+use crate::config::{Config, IndentStyle};
+use crate::error::Error;
 use crate::ring::RingBuffer;
-use crate::{MARGIN, MIN_SPACE};
+#[cfg(feature = "span-locations")]
+use crate::source_map::{Recorder, SourceMap};
 use std::borrow::Cow;
 use std::cmp;
+#[cfg(feature = "verbatim")]
+use std::collections::HashMap;
 use std::collections::VecDeque;
+use std::fmt;
 use std::iter;
 
 #[derive(Clone, Copy, PartialEq)]
@@ -23,6 +30,10 @@ pub struct BreakToken {
     pub no_break: Option<char>,
     pub if_nonempty: bool,
     pub never_break: bool,
+    // Whether the text between the previous break and this one only fits on a
+    // line if there is also room for `pre_break`, as for the `\` continuing
+    // a string literal, which must not go past the margin
+    pub reserve_pre_break: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -47,7 +58,14 @@ enum PrintFrame {
 
 pub const SIZE_INFINITY: isize = 0xffff;
 
-pub struct Printer {
+pub struct Printer<'a> {
+    pub config: Config,
+    // Target line width
+    margin: isize,
+    // Number of columns increment at each level of block indentation
+    pub indent_unit: isize,
+    // Every line is allowed at least this much space, even if highly indented
+    min_space: isize,
     out: String,
     // Number of spaces left on line
     space: isize,
@@ -69,19 +87,58 @@ pub struct Printer {
     indent: usize,
     // Buffered indentation to avoid writing trailing whitespace
     pending_indentation: usize,
+    // Offsets of the boxes begun but not yet ended at the scan position
+    open_offsets: Vec<isize>,
+    // First syntax tree node that could not be printed
+    pub error: Option<Error>,
+    // Destination for completed lines when streaming, instead of accumulating
+    // the whole output in `out`
+    sink: Option<&'a mut dyn fmt::Write>,
+    sink_result: fmt::Result,
+    // Comments from the original source text not yet printed, in source order
+    pub comments: VecDeque<Comment>,
+    // While formatting macro input that must keep exactly its original tokens,
+    // lists broken across lines do not get a trailing comma, so that the
+    // tokens do not depend on the line width
+    pub exact_tokens: bool,
+    // Number of string tokens scanned so far
+    #[cfg(feature = "span-locations")]
+    pub scanned_strings: usize,
+    // Output positions of nodes, when building a source map
+    #[cfg(feature = "span-locations")]
+    pub source_map: Option<Recorder>,
+    // While formatting a macro_rules transcriber whose metavariables have been
+    // replaced by placeholders, the text following the closing parenthesis of
+    // each `$(...)` repetition
+    #[cfg(feature = "verbatim")]
+    pub repetitions: Option<Vec<String>>,
+    // For each macro not from the standard library, keyed by delimiter and
+    // arguments, which parser formats the arguments without changing their
+    // tokens, if any
+    #[cfg(feature = "verbatim")]
+    pub unknown_macros: HashMap<(char, String), Option<usize>>,
 }
 
+// When streaming, completed lines are written to the sink once this many bytes
+// of output are buffered.
+const SINK_CHUNK: usize = 8192;
+
 #[derive(Clone)]
 struct BufEntry {
     token: Token,
     size: isize,
 }
 
-impl Printer {
-    pub fn new() -> Self {
+impl<'a> Printer<'a> {
+    pub fn new(config: &Config) -> Self {
+        let margin = config.max_width as isize;
         Printer {
+            config: config.clone(),
+            margin,
+            indent_unit: config.indent_width as isize,
+            min_space: config.min_space as isize,
             out: String::new(),
-            space: MARGIN,
+            space: margin,
             buf: RingBuffer::new(),
             left_total: 0,
             right_total: 0,
@@ -89,15 +146,74 @@ impl Printer {
             print_stack: Vec::new(),
             indent: 0,
             pending_indentation: 0,
+            open_offsets: Vec::new(),
+            error: None,
+            sink: None,
+            sink_result: Ok(()),
+            comments: VecDeque::new(),
+            exact_tokens: false,
+            #[cfg(feature = "span-locations")]
+            scanned_strings: 0,
+            #[cfg(feature = "span-locations")]
+            source_map: None,
+            #[cfg(feature = "verbatim")]
+            repetitions: None,
+            #[cfg(feature = "verbatim")]
+            unknown_macros: HashMap::new(),
+        }
+    }
+
+    pub fn with_sink(config: &Config, sink: &'a mut dyn fmt::Write) -> Self {
+        let mut printer = Printer::new(config);
+        printer.sink = Some(sink);
+        printer
+    }
+
+    pub fn eof(mut self) -> Result<String, Error> {
+        self.finish();
+        match self.error {
+            None => Ok(self.out),
+            Some(error) => Err(error),
+        }
+    }
+
+    #[cfg(feature = "span-locations")]
+    pub fn eof_with_source_map(mut self) -> Result<(String, SourceMap), Error> {
+        self.finish();
+        let recorder = self.source_map.take().unwrap_or_default();
+        match self.error {
+            None => {
+                let source_map = recorder.finish(&self.out);
+                Ok((self.out, source_map))
+            }
+            Some(error) => Err(error),
         }
     }
 
-    pub fn eof(mut self) -> String {
+    pub fn eof_to_sink(mut self) -> Result<fmt::Result, Error> {
+        self.finish();
+        self.flush_sink();
+        match self.error {
+            None => Ok(self.sink_result),
+            Some(error) => Err(error),
+        }
+    }
+
+    fn finish(&mut self) {
         if !self.scan_stack.is_empty() {
             self.check_stack(0);
             self.advance_left();
         }
-        self.out
+    }
+
+    fn flush_sink(&mut self) {
+        if let Some(sink) = &mut self.sink {
+            // After a node that cannot be printed, nothing more is written.
+            if self.sink_result.is_ok() && self.error.is_none() {
+                self.sink_result = sink.write_str(&self.out);
+            }
+            self.out.clear();
+        }
     }
 
     pub fn scan_begin(&mut self, token: BeginToken) {
@@ -106,6 +222,7 @@ impl Printer {
             self.right_total = 1;
             self.buf.clear();
         }
+        self.open_offsets.push(token.offset);
         let right = self.buf.push(BufEntry {
             token: Token::Begin(token),
             size: -self.right_total,
@@ -114,6 +231,7 @@ impl Printer {
     }
 
     pub fn scan_end(&mut self) {
+        self.open_offsets.pop();
         if self.scan_stack.is_empty() {
             self.print_end();
         } else {
@@ -150,6 +268,12 @@ impl Printer {
             self.right_total = 1;
             self.buf.clear();
         } else {
+            if let (true, Some(pre_break)) = (token.reserve_pre_break, token.pre_break) {
+                let index = *self.scan_stack.back().unwrap();
+                if let Token::Break(_) = self.buf[index].token {
+                    self.buf[index].size += pre_break.len_utf8() as isize;
+                }
+            }
             self.check_stack(0);
         }
         let right = self.buf.push(BufEntry {
@@ -161,6 +285,10 @@ impl Printer {
     }
 
     pub fn scan_string(&mut self, string: Cow<'static, str>) {
+        #[cfg(feature = "span-locations")]
+        {
+            self.scanned_strings += 1;
+        }
         if self.scan_stack.is_empty() {
             self.print_string(string);
         } else {
@@ -174,6 +302,13 @@ impl Printer {
         }
     }
 
+    // Indentation of a line following a hard break at the scan position. Every
+    // box containing a hard break is broken, so this is the sum of the offsets
+    // of the boxes still open.
+    pub fn scan_indent(&self) -> isize {
+        self.open_offsets.iter().sum()
+    }
+
     #[track_caller]
     pub fn offset(&mut self, offset: isize) {
         match &mut self.buf.last_mut().token {
@@ -198,6 +333,10 @@ impl Printer {
                                     token: Token::String(Cow::Borrowed("")),
                                     size: SIZE_INFINITY,
                                 });
+                                #[cfg(feature = "span-locations")]
+                                {
+                                    self.scanned_strings += 1;
+                                }
                                 self.right_total += SIZE_INFINITY;
                             }
                         }
@@ -360,9 +499,12 @@ impl Printer {
                 self.out.push('·');
             }
             self.out.push('\n');
+            if self.out.len() >= SINK_CHUNK {
+                self.flush_sink();
+            }
             let indent = self.indent as isize + token.offset;
             self.pending_indentation = usize::try_from(indent).unwrap();
-            self.space = cmp::max(MARGIN - indent, MIN_SPACE);
+            self.space = cmp::max(self.margin - indent, self.min_space);
             if !token.post_break.is_empty() {
                 self.print_indent();
                 self.out.push_str(token.post_break);
@@ -373,14 +515,33 @@ impl Printer {
 
     fn print_string(&mut self, string: Cow<'static, str>) {
         self.print_indent();
+        #[cfg(feature = "span-locations")]
+        if let Some(recorder) = &mut self.source_map {
+            let start = self.out.len();
+            recorder.strings.push(start..start + string.len());
+        }
         self.out.push_str(&string);
         self.space -= string.len() as isize;
     }
 
     fn print_indent(&mut self) {
-        self.out.reserve(self.pending_indentation);
-        self.out
-            .extend(iter::repeat(' ').take(self.pending_indentation));
+        let (tabs, spaces) = match self.config.indent_style {
+            IndentStyle::Tabs if self.indent_unit > 0 && self.at_line_start() => {
+                let unit = self.indent_unit as usize;
+                (
+                    self.pending_indentation / unit,
+                    self.pending_indentation % unit,
+                )
+            }
+            IndentStyle::Spaces | IndentStyle::Tabs => (0, self.pending_indentation),
+        };
+        self.out.reserve(tabs + spaces);
+        self.out.extend(iter::repeat('\t').take(tabs));
+        self.out.extend(iter::repeat(' ').take(spaces));
         self.pending_indentation = 0;
     }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
 }
@@ -1,25 +1,167 @@
-use crate::algorithm::Printer;
+use crate::algorithm::{BreakToken, Printer};
+#[cfg(not(feature = "verbatim"))]
+use crate::config::Config;
+use crate::config::ImportsGranularity;
 use crate::fixup::FixupContext;
+use crate::iter::IterDelimited;
 use crate::path::PathKind;
-use crate::INDENT;
+#[cfg(feature = "verbatim")]
+use doc_code::format_doc_code;
 use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
+use std::cmp;
+use std::iter::Peekable;
 use syn::{AttrStyle, Attribute, Expr, Lit, MacroDelimiter, Meta, MetaList, MetaNameValue};
 
-impl Printer {
+impl Printer<'_> {
     pub fn outer_attrs(&mut self, attrs: &[Attribute]) {
-        for attr in attrs {
-            if let AttrStyle::Outer = attr.style {
-                self.attr(attr);
+        let mut attrs = attrs
+            .iter()
+            .filter(|attr| matches!(attr.style, AttrStyle::Outer))
+            .peekable();
+        self.attrs(&mut attrs);
+    }
+
+    pub fn inner_attrs(&mut self, attrs: &[Attribute]) {
+        let mut attrs = attrs
+            .iter()
+            .filter(|attr| matches!(attr.style, AttrStyle::Inner(_)))
+            .peekable();
+        self.attrs(&mut attrs);
+    }
+
+    fn attrs<'a>(&mut self, attrs: &mut Peekable<impl Iterator<Item = &'a Attribute>>) {
+        while let Some(attr) = attrs.next() {
+            if self.config.wrap_doc_comments || self.config.format_doc_code_blocks {
+                if let Some(first) = line_doc_comment(attr) {
+                    let mut lines = vec![first];
+                    while let Some(line) = attrs.peek().and_then(|attr| line_doc_comment(attr)) {
+                        lines.push(line);
+                        attrs.next();
+                    }
+                    self.doc_comment_lines(&attr.style, &lines);
+                    continue;
+                }
             }
+            self.attr(attr);
         }
     }
 
-    pub fn inner_attrs(&mut self, attrs: &[Attribute]) {
-        for attr in attrs {
-            if let AttrStyle::Inner(_) = attr.style {
-                self.attr(attr);
+    // Prints consecutive `///` or `//!` lines, reflowing each paragraph of
+    // plain text to the margin and formatting Rust code blocks as configured.
+    // Tables, lists, headings, block quotes, link reference definitions,
+    // indented lines and other code blocks are printed as written.
+    fn doc_comment_lines(&mut self, style: &AttrStyle, lines: &[String]) {
+        let (prefix, continuation) = match style {
+            AttrStyle::Outer => ("///", "/// "),
+            AttrStyle::Inner(_) => ("//!", "//! "),
+        };
+        let mut fence: Option<&str> = None;
+        // Indentation of the opening fence, and the lines so far, of a code
+        // block to be formatted.
+        let mut code: Option<(&str, Vec<&str>)> = None;
+        let mut paragraph: Vec<&str> = Vec::new();
+        for line in lines {
+            let text = line.trim_start();
+            let reflow = if let Some(marker) = fence {
+                if text.starts_with(marker) {
+                    fence = None;
+                    if let Some((indent, code)) = code.take() {
+                        self.doc_code_block(prefix, indent, &code);
+                    }
+                } else if let Some((_indent, code)) = &mut code {
+                    code.push(line);
+                    continue;
+                }
+                false
+            } else if let Some(marker) = code_fence(text) {
+                fence = Some(marker);
+                if self.config.format_doc_code_blocks && is_rust_info(&text[marker.len()..]) {
+                    code = Some((&line[..line.len() - text.len()], Vec::new()));
+                }
+                false
+            } else {
+                self.config.wrap_doc_comments && is_plain_text(line)
+            };
+            if reflow {
+                paragraph.extend(line.split_whitespace());
+                if !ends_with_hard_break(line) {
+                    continue;
+                }
+            }
+            self.doc_comment_paragraph(continuation, &mut paragraph);
+            if !reflow {
+                self.doc_comment_line(prefix, line);
+            }
+        }
+        self.doc_comment_paragraph(continuation, &mut paragraph);
+        if let Some((_indent, code)) = code {
+            // Unterminated code block.
+            for line in code {
+                self.doc_comment_line(prefix, line);
+            }
+        }
+    }
+
+    fn doc_comment_line(&mut self, prefix: &'static str, line: &str) {
+        self.word(prefix);
+        self.word(line.trim_end_matches(' ').to_owned());
+        self.hardbreak();
+    }
+
+    fn doc_code_block(&mut self, prefix: &'static str, indent: &str, code: &[&str]) {
+        let mut config = self.config.clone();
+        let width = self.config.max_width as isize
+            - self.scan_indent()
+            - prefix.len() as isize
+            - indent.len() as isize;
+        config.max_width = cmp::max(width, self.config.min_space as isize) as usize;
+        config.header = None;
+        // Imports in an example are part of what it shows, so they keep the
+        // order and grouping the author gave them.
+        config.reorder_imports = false;
+        config.imports_granularity = ImportsGranularity::Preserve;
+        match format_doc_code(indent, code, &config) {
+            Some(formatted) => {
+                for line in formatted {
+                    self.word(prefix);
+                    if !line.is_empty() {
+                        self.word(format!("{indent}{line}"));
+                    }
+                    self.hardbreak();
+                }
+            }
+            None => {
+                for line in code {
+                    self.doc_comment_line(prefix, line);
+                }
+            }
+        }
+    }
+
+    fn doc_comment_paragraph(&mut self, prefix: &'static str, words: &mut Vec<&str>) {
+        if words.is_empty() {
+            return;
+        }
+        self.ibox(0);
+        self.word(prefix);
+        for word in words.drain(..).delimited() {
+            if word.is_first {
+                // Follows the prefix.
+            } else if is_plain_text(&word) {
+                self.scan_break(BreakToken {
+                    blank_space: 1,
+                    post_break: prefix,
+                    ..BreakToken::default()
+                });
+            } else {
+                // Starting a line with this word would turn the line into a
+                // list item, heading or other block.
+                self.word(" ");
             }
+            self.word(word.to_owned());
         }
+        self.end();
+        self.hardbreak();
     }
 
     fn attr(&mut self, attr: &Attribute) {
@@ -91,6 +233,12 @@ impl Printer {
 
     fn meta_list(&mut self, meta: &MetaList) {
         self.path(&meta.path, PathKind::Simple);
+        if self.config.wrap_attribute_args && matches!(meta.delimiter, MacroDelimiter::Paren(_)) {
+            if let Some(nested) = nested_metas(meta.tokens.clone()) {
+                self.nested_metas(&nested);
+                return;
+            }
+        }
         let delimiter = match meta.delimiter {
             MacroDelimiter::Paren(_) => Delimiter::Parenthesis,
             MacroDelimiter::Brace(_) => Delimiter::Brace,
@@ -100,6 +248,36 @@ impl Printer {
         self.attr_tokens(TokenStream::from(TokenTree::Group(group)));
     }
 
+    // Prints a parenthesized list of metas like the arguments of a function
+    // call. A single nested list is written on the same line as the
+    // parenthesis, as in `#[cfg(all(` ... `))]`.
+    fn nested_metas(&mut self, nested: &[NestedMeta]) {
+        self.word("(");
+        if let [meta @ NestedMeta::List(..)] = nested {
+            self.nested_meta(meta);
+        } else {
+            self.cbox(self.indent_unit);
+            self.zerobreak();
+            for meta in nested.iter().delimited() {
+                self.nested_meta(&meta);
+                self.trailing_comma(meta.is_last);
+            }
+            self.offset(-self.indent_unit);
+            self.end();
+        }
+        self.word(")");
+    }
+
+    fn nested_meta(&mut self, meta: &NestedMeta) {
+        match meta {
+            NestedMeta::List(path, nested) => {
+                self.attr_tokens(path.clone());
+                self.nested_metas(nested);
+            }
+            NestedMeta::Tokens(tokens) => self.attr_tokens(tokens.clone()),
+        }
+    }
+
     fn meta_name_value(&mut self, meta: &MetaNameValue) {
         self.path(&meta.path, PathKind::Simple);
         self.word(" = ");
@@ -119,7 +297,9 @@ impl Printer {
         }
 
         use State::*;
-        let mut state = Word;
+        // No space before the first token, which is preceded by the attribute's
+        // path or by the `(` or `,` of a list printed by `nested_metas`.
+        let mut state = Punct;
 
         while let Some((tokens, delimiter)) = stack.last_mut() {
             match tokens.next() {
@@ -161,7 +341,7 @@ impl Printer {
                     match delimiter {
                         Delimiter::Parenthesis => {
                             self.word("(");
-                            self.cbox(INDENT);
+                            self.cbox(self.indent_unit);
                             self.zerobreak();
                             state = Punct;
                         }
@@ -184,7 +364,7 @@ impl Printer {
                             if state != TrailingComma {
                                 self.zerobreak();
                             }
-                            self.offset(-INDENT);
+                            self.offset(-self.indent_unit);
                             self.end();
                             self.word(")");
                             state = Punct;
@@ -209,6 +389,80 @@ impl Printer {
     }
 }
 
+// The text of a doc attribute that the printer writes as a `///` or `//!`
+// line.
+fn line_doc_comment(attr: &Attribute) -> Option<String> {
+    let doc = value_of_attribute("doc", attr)?;
+    let line = !doc.contains('\n')
+        && match attr.style {
+            AttrStyle::Outer => !doc.starts_with('/'),
+            AttrStyle::Inner(_) => true,
+        };
+    line.then_some(doc)
+}
+
+// The opening fence of a Markdown code block, if this line is one.
+pub fn code_fence(text: &str) -> Option<&str> {
+    ["```", "~~~"]
+        .into_iter()
+        .find(|marker| text.starts_with(marker))
+        .map(|marker| {
+            let ch = marker.chars().next().unwrap();
+            &text[..text.len() - text.trim_start_matches(ch).len()]
+        })
+}
+
+// Whether the info string after the opening fence of a Markdown code block
+// marks it as Rust code, as rustdoc does for an untagged code block.
+pub fn is_rust_info(info: &str) -> bool {
+    let mut tags = info.split([',', ' ', '\t']).filter(|tag| !tag.is_empty());
+    tags.next().map_or(true, |tag| tag == "rust")
+}
+
+// Whether a line of a Rust code block is hidden from the rendered
+// documentation.
+#[cfg_attr(not(any(feature = "verbatim", feature = "verify")), allow(dead_code))]
+pub fn is_hidden_code_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed == "#" || trimmed.starts_with("# ") || trimmed.starts_with("#\t")
+}
+
+// Whether a line of a doc comment is part of a Markdown paragraph that can be
+// reflowed, as opposed to a blank line, a table row, list item, heading,
+// thematic break, block quote, HTML, code fence, link reference definition,
+// or indented text such as a list item's continuation or an indented code
+// block.
+fn is_plain_text(line: &str) -> bool {
+    let text = line.strip_prefix(' ').unwrap_or(line);
+    let Some(first) = text.chars().next() else {
+        return false;
+    };
+    let marker_end = |rest: &str| rest.is_empty() || rest.starts_with(' ');
+    let heading = first == '#' && marker_end(text.trim_start_matches('#'));
+    let bullet = "-*+".contains(first) && marker_end(&text[1..]);
+    let ordered = first.is_ascii_digit()
+        && text
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .strip_prefix(['.', ')'])
+            .is_some_and(marker_end);
+    let thematic_break = text.chars().all(|ch| "-*_= ".contains(ch));
+    let link_reference = first == '[' && text.contains("]:");
+    !(first.is_whitespace()
+        || "|><".contains(first)
+        || code_fence(text).is_some()
+        || heading
+        || bullet
+        || ordered
+        || thematic_break
+        || link_reference)
+}
+
+// Whether a line of a doc comment ends in a Markdown hard line break, two
+// spaces or a backslash, after which the paragraph continues on a new line.
+fn ends_with_hard_break(line: &str) -> bool {
+    line.ends_with("  ") || line.ends_with('\\')
+}
+
 fn value_of_attribute(requested: &str, attr: &Attribute) -> Option<String> {
     let value = match &attr.meta {
         Meta::NameValue(meta) if meta.path.is_ident(requested) => &meta.value,
@@ -263,6 +517,79 @@ fn trim_interior_trailing_spaces(doc: &mut String) {
     *doc = trimmed;
 }
 
+// An element of an attribute's parenthesized argument list.
+enum NestedMeta {
+    // A path followed by a parenthesized list of metas, as in `all(a, b)`.
+    List(TokenStream, Vec<NestedMeta>),
+    // A path, a path followed by some other parenthesized tokens, or a path
+    // followed by `=` and a value, printed token by token.
+    Tokens(TokenStream),
+}
+
+// Splits the arguments of a meta list at its commas, if every argument is a
+// path, a path followed by parenthesized tokens, or `path = value`. Returns
+// None for anything else, such as a bare literal.
+fn nested_metas(tokens: TokenStream) -> Option<Vec<NestedMeta>> {
+    let mut nested = Vec::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while tokens.peek().is_some() {
+        let mut path = TokenStream::new();
+        let mut leading_colons = true;
+        loop {
+            if is_punct(tokens.peek(), ':') {
+                let colon = tokens.next();
+                if !is_punct(tokens.peek(), ':') {
+                    return None;
+                }
+                path.extend(colon);
+                path.extend(tokens.next());
+            } else if !leading_colons {
+                break;
+            }
+            leading_colons = false;
+            match tokens.next() {
+                Some(ident @ TokenTree::Ident(_)) => path.extend([ident]),
+                _ => return None,
+            }
+        }
+
+        nested.push(match tokens.next_if(|token| !is_punct(Some(token), ',')) {
+            None => NestedMeta::Tokens(path),
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                match nested_metas(group.stream()) {
+                    Some(list) if !list.is_empty() => NestedMeta::List(path, list),
+                    _ => {
+                        path.extend([TokenTree::Group(group)]);
+                        NestedMeta::Tokens(path)
+                    }
+                }
+            }
+            Some(eq @ TokenTree::Punct(_)) if is_punct(Some(&eq), '=') => {
+                path.extend([eq]);
+                let mut value = false;
+                while let Some(token) = tokens.next_if(|token| !is_punct(Some(token), ',')) {
+                    path.extend([token]);
+                    value = true;
+                }
+                if !value {
+                    return None;
+                }
+                NestedMeta::Tokens(path)
+            }
+            Some(_) => return None,
+        });
+
+        if tokens.next_if(|token| is_punct(Some(token), ',')).is_none() && tokens.peek().is_some() {
+            return None;
+        }
+    }
+    Some(nested)
+}
+
+fn is_punct(token: Option<&TokenTree>, ch: char) -> bool {
+    matches!(token, Some(TokenTree::Punct(punct)) if punct.as_char() == ch)
+}
+
 fn can_be_block_comment(value: &str) -> bool {
     let mut depth = 0usize;
     let bytes = value.as_bytes();
@@ -286,3 +613,94 @@ fn can_be_block_comment(value: &str) -> bool {
 
     depth == 0 && !value.ends_with('/')
 }
+
+#[cfg(not(feature = "verbatim"))]
+fn format_doc_code(indent: &str, code: &[&str], config: &Config) -> Option<Vec<String>> {
+    let _ = (indent, code, config);
+    None
+}
+
+#[cfg(feature = "verbatim")]
+mod doc_code {
+    use super::is_hidden_code_line;
+    use crate::algorithm::Printer;
+    #[cfg(feature = "span-locations")]
+    use crate::comment;
+    use crate::config::Config;
+    use std::iter;
+    use syn::parse::Parser;
+    use syn::{token, Block};
+
+    // Formats the lines of a Rust code block of a doc comment, which are
+    // indented at least as far as its opening fence. Each run of lines between
+    // the lines hidden with `# ` is formatted on its own, as a file or else as
+    // statements.
+    pub fn format_doc_code(indent: &str, code: &[&str], config: &Config) -> Option<Vec<String>> {
+        let mut formatted = Vec::new();
+        let mut run = Vec::new();
+        for line in code {
+            let line = if line.trim().is_empty() {
+                ""
+            } else {
+                line.strip_prefix(indent)?
+            };
+            if is_hidden_code_line(line) {
+                format_doc_code_run(&run, config, &mut formatted)?;
+                run.clear();
+                formatted.push(line.trim_end().to_owned());
+            } else {
+                run.push(line);
+            }
+        }
+        format_doc_code_run(&run, config, &mut formatted)?;
+        Some(formatted)
+    }
+
+    fn format_doc_code_run(
+        run: &[&str],
+        config: &Config,
+        formatted: &mut Vec<String>,
+    ) -> Option<()> {
+        let leading = run.iter().take_while(|line| line.is_empty()).count();
+        if leading == run.len() {
+            formatted.extend(run.iter().map(|_| String::new()));
+            return Some(());
+        }
+        let trailing = run.iter().rev().take_while(|line| line.is_empty()).count();
+        let source = run[leading..run.len() - trailing].join("\n");
+
+        let mut p = Printer::new(config);
+        #[cfg(feature = "span-locations")]
+        {
+            p.comments = comment::parse(&source);
+        }
+        #[cfg(not(feature = "span-locations"))]
+        if source.contains("//") || source.contains("/*") {
+            return None;
+        }
+        if let Ok(file) = syn::parse_file(&source) {
+            p.file(&file);
+        } else {
+            let stmts = Block::parse_within.parse_str(&source).ok()?;
+            let block = Block {
+                brace_token: token::Brace::default(),
+                stmts,
+            };
+            p.cbox(0);
+            p.stmts(&block);
+            p.remaining_comments();
+            p.end();
+        }
+        let output = p.eof().ok()?;
+        if output.lines().any(is_hidden_code_line) {
+            // Macro input printed token by token, such as `#var` in `quote!`,
+            // would be hidden from the rendered documentation as `# var`.
+            return None;
+        }
+
+        formatted.extend(iter::repeat(String::new()).take(leading));
+        formatted.extend(output.lines().map(str::to_owned));
+        formatted.extend(iter::repeat(String::new()).take(trailing));
+        Some(())
+    }
+}
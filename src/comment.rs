@@ -0,0 +1,293 @@
+use crate::algorithm::Printer;
+#[cfg(feature = "span-locations")]
+use crate::span::Locate;
+use proc_macro2::extra::DelimSpan;
+
+pub struct Comment {
+    // Byte offset of the start of the comment in the source text
+    offset: usize,
+    // Source lines on which the comment begins and ends, 1-based
+    #[cfg(feature = "span-locations")]
+    line: usize,
+    #[cfg(feature = "span-locations")]
+    end_line: usize,
+    text: String,
+    block: bool,
+}
+
+impl Printer<'_> {
+    #[cfg(not(feature = "span-locations"))]
+    pub fn leading_comments<T: ?Sized>(&mut self, node: &T) {
+        let _ = node;
+    }
+
+    #[cfg(feature = "span-locations")]
+    pub fn leading_comments<T: Locate + ?Sized>(&mut self, node: &T) {
+        if self.comments.is_empty() {
+            return;
+        }
+        let offset = node.first_span().byte_range().start;
+        self.comments_before(offset);
+    }
+
+    #[cfg(not(feature = "span-locations"))]
+    pub fn trailing_comments(&mut self, delim: DelimSpan) {
+        let _ = delim;
+    }
+
+    #[cfg(feature = "span-locations")]
+    pub fn trailing_comments(&mut self, delim: DelimSpan) {
+        if self.comments.is_empty() {
+            return;
+        }
+        let offset = delim.close().byte_range().start;
+        self.comments_before(offset);
+    }
+
+    // Whether a comment remains to be printed before the closing delimiter.
+    // Layouts that put a delimited group on one line are only used without
+    // one, since printing a comment breaks the line.
+    #[cfg(not(feature = "span-locations"))]
+    pub fn has_comments_within(&self, delim: DelimSpan) -> bool {
+        let _ = delim;
+        false
+    }
+
+    #[cfg(feature = "span-locations")]
+    pub fn has_comments_within(&self, delim: DelimSpan) -> bool {
+        let offset = delim.close().byte_range().start;
+        self.comments
+            .front()
+            .is_some_and(|comment| comment.offset < offset)
+    }
+
+    #[cfg(not(feature = "span-locations"))]
+    pub fn blank_line_between<T: ?Sized>(&mut self, prev: &T, next: &T) {
+        let _ = (prev, next);
+    }
+
+    #[cfg(feature = "span-locations")]
+    pub fn blank_line_between<T: Locate + ?Sized>(&mut self, prev: &T, next: &T) {
+        if !self.config.preserve_blank_lines {
+            return;
+        }
+        let mut line = prev.last_span().end().line;
+        let next = next.first_span();
+        let offset = next.byte_range().start;
+        while let Some(comment) = self.comments.front() {
+            if comment.offset >= offset {
+                break;
+            }
+            let comment = self.comments.pop_front().unwrap();
+            if comment.line > line + 1 {
+                self.hardbreak();
+            }
+            line = comment.end_line;
+            self.comment(comment);
+        }
+        if next.start().line > line + 1 {
+            self.hardbreak();
+        }
+    }
+
+    pub fn remaining_comments(&mut self) {
+        self.comments_before(usize::MAX);
+    }
+
+    fn comments_before(&mut self, offset: usize) {
+        while let Some(comment) = self.comments.front() {
+            if comment.offset >= offset {
+                break;
+            }
+            let comment = self.comments.pop_front().unwrap();
+            self.comment(comment);
+        }
+    }
+
+    fn comment(&mut self, comment: Comment) {
+        if comment.block {
+            self.word(comment.text);
+        } else {
+            self.word(comment.text.trim_end().to_owned());
+        }
+        self.hardbreak();
+    }
+}
+
+#[cfg(feature = "span-locations")]
+mod scan {
+    use super::Comment;
+    use std::collections::VecDeque;
+
+    // Collects the non-doc comments in the same text that syn::parse_file would
+    // parse, with offsets consistent with the byte ranges of the parsed spans.
+    pub fn parse(mut source: &str) -> VecDeque<Comment> {
+        if let Some(rest) = source.strip_prefix('\u{feff}') {
+            source = rest;
+        }
+        if source.starts_with("#!") && !source[2..].trim_start().starts_with('[') {
+            source = source.find('\n').map_or("", |i| &source[i..]);
+        }
+
+        let bytes = source.as_bytes();
+        let mut comments = VecDeque::new();
+        let mut lines = Lines {
+            bytes,
+            offset: 0,
+            line: 1,
+        };
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                    let end = source[i..].find('\n').map_or(bytes.len(), |len| i + len);
+                    let text = &source[i..end];
+                    let is_doc = (text.starts_with("///") && !text.starts_with("////"))
+                        || text.starts_with("//!");
+                    if !is_doc {
+                        let line = lines.at(i);
+                        comments.push_back(Comment {
+                            offset: i,
+                            line,
+                            end_line: line,
+                            text: text.to_owned(),
+                            block: false,
+                        });
+                    }
+                    i = end;
+                }
+                b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                    let end = block_comment_end(bytes, i);
+                    let text = &source[i..end];
+                    let is_doc =
+                        (text.starts_with("/**") && !text.starts_with("/***") && text != "/**/")
+                            || text.starts_with("/*!");
+                    if !is_doc {
+                        comments.push_back(Comment {
+                            offset: i,
+                            line: lines.at(i),
+                            end_line: lines.at(end),
+                            text: text.to_owned(),
+                            block: true,
+                        });
+                    }
+                    i = end;
+                }
+                b'"' => i = quoted_end(bytes, i + 1, b'"'),
+                b'r' | b'b' | b'c' if !is_ident_continue(bytes, i) => {
+                    i = prefixed_literal_end(bytes, i);
+                }
+                b'\'' => i = char_or_lifetime_end(source, i),
+                _ => i += 1,
+            }
+        }
+        comments
+    }
+
+    // Computes line numbers for a sequence of increasing offsets.
+    struct Lines<'a> {
+        bytes: &'a [u8],
+        offset: usize,
+        line: usize,
+    }
+
+    impl Lines<'_> {
+        fn at(&mut self, offset: usize) -> usize {
+            let newlines = self.bytes[self.offset..offset]
+                .iter()
+                .filter(|&&b| b == b'\n');
+            self.line += newlines.count();
+            self.offset = offset;
+            self.line
+        }
+    }
+
+    fn is_ident_continue(bytes: &[u8], i: usize) -> bool {
+        i > 0 && (bytes[i - 1] == b'_' || bytes[i - 1].is_ascii_alphanumeric())
+    }
+
+    fn block_comment_end(bytes: &[u8], start: usize) -> usize {
+        let mut depth = 0usize;
+        let mut i = start;
+        while i + 1 < bytes.len() {
+            if bytes[i] == b'/' && bytes[i + 1] == b'*' {
+                depth += 1;
+                i += 2;
+            } else if bytes[i] == b'*' && bytes[i + 1] == b'/' {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return i;
+                }
+            } else {
+                i += 1;
+            }
+        }
+        bytes.len()
+    }
+
+    // Returns the offset just past the closing quote, given the offset just past
+    // the opening quote.
+    fn quoted_end(bytes: &[u8], mut i: usize, quote: u8) -> usize {
+        while i < bytes.len() {
+            if bytes[i] == b'\\' {
+                i += 2;
+            } else if bytes[i] == quote {
+                return i + 1;
+            } else {
+                i += 1;
+            }
+        }
+        bytes.len()
+    }
+
+    // Handles r"", r#""#, b"", br"", b'', c"", cr"" literals, as well as ordinary
+    // identifiers beginning with one of those letters.
+    fn prefixed_literal_end(bytes: &[u8], start: usize) -> usize {
+        let mut i = start;
+        if bytes[i] == b'b' || bytes[i] == b'c' {
+            i += 1;
+            match bytes.get(i) {
+                Some(b'"') => return quoted_end(bytes, i + 1, b'"'),
+                Some(b'\'') if bytes[start] == b'b' => return quoted_end(bytes, i + 1, b'\''),
+                Some(b'r') => {}
+                _ => return i,
+            }
+        }
+        i += 1;
+        let hashes_start = i;
+        while bytes.get(i) == Some(&b'#') {
+            i += 1;
+        }
+        let hashes = i - hashes_start;
+        if bytes.get(i) != Some(&b'"') {
+            return start + 1;
+        }
+        i += 1;
+        while i < bytes.len() {
+            if bytes[i] == b'"'
+                && bytes.len() - (i + 1) >= hashes
+                && bytes[i + 1..i + 1 + hashes].iter().all(|&b| b == b'#')
+            {
+                return i + 1 + hashes;
+            }
+            i += 1;
+        }
+        bytes.len()
+    }
+
+    fn char_or_lifetime_end(source: &str, start: usize) -> usize {
+        let mut chars = source[start + 1..].char_indices();
+        match chars.next() {
+            Some((_, '\\')) => quoted_end(source.as_bytes(), start + 1, b'\''),
+            Some((_, ch)) => match chars.next() {
+                Some((i, '\'')) => start + 1 + i + 1,
+                _ => start + 1 + ch.len_utf8(),
+            },
+            None => source.len(),
+        }
+    }
+}
+
+#[cfg(feature = "span-locations")]
+pub use self::scan::parse;
@@ -0,0 +1,365 @@
+/// Layout options for [`unparse_with`][crate::unparse_with].
+///
+/// The default configuration produces the same output as
+/// [`unparse`][crate::unparse].
+///
+/// ```
+/// let mut config = prettyplease::Config::default();
+/// config.max_width = 100;
+/// ```
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Config {
+    /// Target line width.
+    ///
+    /// Default: 89.
+    pub max_width: usize,
+
+    /// Number of columns by which each level of block indentation increases.
+    ///
+    /// Default: 4.
+    pub indent_width: usize,
+
+    /// Every line is allowed at least this much space, even if highly
+    /// indented.
+    ///
+    /// Default: 60.
+    pub min_space: usize,
+
+    /// Maximum width of a struct literal, such as `Point { x: 0, y: 0 }`, that
+    /// is printed on a single line. Wider struct literals put each field on
+    /// its own line, even if they would fit within the margin.
+    ///
+    /// Default: 34.
+    pub struct_lit_width: usize,
+
+    /// Characters used for leading indentation.
+    ///
+    /// Default: [`IndentStyle::Spaces`].
+    pub indent_style: IndentStyle,
+
+    /// Print syntax tree nodes that are not recognized, such as `Verbatim`
+    /// nodes containing syntax newer than this crate, as a best-effort
+    /// sequence of their raw tokens instead of failing.
+    ///
+    /// Default: false.
+    pub raw_token_fallback: bool,
+
+    /// Keep a single blank line between consecutive items of a file or module,
+    /// and between consecutive statements of a block, wherever the original
+    /// source had one or more blank lines.
+    ///
+    /// This relies on the syntax tree carrying real line information, which
+    /// requires it to have been parsed from source text while the
+    /// `span-locations` feature of this crate is enabled. Otherwise this option
+    /// has no effect.
+    ///
+    /// Default: false.
+    pub preserve_blank_lines: bool,
+
+    /// Sort each run of consecutive `use` declarations in a module or block,
+    /// and the contents of their braced groups, in the order used by rustfmt's
+    /// 2024 style edition, and remove duplicates.
+    ///
+    /// Default: false.
+    pub reorder_imports: bool,
+
+    /// Whether to merge consecutive `use` declarations that share a prefix.
+    /// Declarations with attributes or differing visibility are not merged.
+    ///
+    /// Default: [`ImportsGranularity::Preserve`].
+    pub imports_granularity: ImportsGranularity,
+
+    /// Text to place at the top of a formatted file, such as
+    /// `"// @generated"`. Each line of the header is printed on its own line,
+    /// after the shebang line if there is one.
+    ///
+    /// The header is printed exactly as given and is not checked to consist of
+    /// comments. Text that is not a comment or attribute ends up in the
+    /// formatted code, which then no longer parses.
+    ///
+    /// Default: None.
+    pub header: Option<String>,
+
+    /// Function-like macros, in addition to those of the standard library,
+    /// whose arguments are Rust syntax to be formatted rather than printed
+    /// token by token.
+    ///
+    /// This requires the `verbatim` feature of this crate. Otherwise this
+    /// option has no effect.
+    ///
+    /// Default: empty.
+    pub macros: MacroRegistry,
+
+    /// Format the arguments of function-like macros that are neither from the
+    /// standard library nor in [`macros`][Config::macros] where they parse as
+    /// comma-separated expressions or as an `[expr; len]` repetition, or,
+    /// inside braces, as statements or items. Arguments whose tokens would
+    /// change by formatting are printed token by token as usual.
+    ///
+    /// This requires the `verbatim` feature of this crate. Otherwise this
+    /// option has no effect.
+    ///
+    /// Default: false.
+    pub format_unknown_macros: bool,
+
+    /// Format the transcriber of each `macro_rules!` or `macro` rule as Rust
+    /// code where it parses as statements or items once its `$var`
+    /// metavariables and `$(...)*` repetitions are set aside. Transcribers
+    /// that do not parse, or whose tokens would change by formatting, are
+    /// printed token by token as usual.
+    ///
+    /// This requires the `verbatim` feature of this crate. Otherwise this
+    /// option has no effect.
+    ///
+    /// Default: false.
+    pub format_macro_rules: bool,
+
+    /// Reflow the paragraphs of `///` and `//!` doc comments to fit within
+    /// [`max_width`][Config::max_width]. Markdown code blocks, tables, lists,
+    /// headings, block quotes and link reference definitions are left as
+    /// written, and a line ending in a hard line break is not joined with the
+    /// next.
+    ///
+    /// Default: false.
+    pub wrap_doc_comments: bool,
+
+    /// Format the fenced code blocks of `///` and `//!` doc comments that are
+    /// untagged or tagged `rust`, as a file or as a sequence of statements,
+    /// within the width that remains after the comment's indentation and
+    /// prefix. Lines hidden from the rendered documentation with `# ` are
+    /// kept as written, and so are the `use` declarations of the block. Code blocks that do not parse are left as written, as
+    /// are code blocks containing comments unless the `span-locations`
+    /// feature of this crate is enabled.
+    ///
+    /// This requires the `verbatim` feature of this crate. Otherwise this
+    /// option has no effect.
+    ///
+    /// Default: false.
+    pub format_doc_code_blocks: bool,
+
+    /// Break string literals that do not fit within the margin at spaces,
+    /// continuing them on the next line after a `\` line continuation. The
+    /// literal remains a single token with the same value. Raw strings and
+    /// strings containing a line break are left as written.
+    ///
+    /// Default: false.
+    pub wrap_string_literals: bool,
+
+    /// Break the arguments of attributes such as `#[derive(...)]`,
+    /// `#[cfg_attr(...)]` and `#[cfg(all(...))]` that do not fit on one line
+    /// one per line, with a trailing comma, like the arguments of a function
+    /// call. This applies to argument lists consisting of paths, `path =
+    /// value` pairs and nested lists of these; other attribute arguments are
+    /// printed as written.
+    ///
+    /// Default: false.
+    pub wrap_attribute_args: bool,
+
+    /// Separate the digits of integer literals, and of the integer part of
+    /// float literals, into groups with `_` when there are more than this
+    /// many: groups of three for decimal and octal, four for hexadecimal and
+    /// binary. With this option set, shorter literals are written without
+    /// `_` between their digits.
+    ///
+    /// Default: None, which keeps digits as written.
+    pub digit_grouping: Option<usize>,
+
+    /// Case of the digits of hexadecimal integer literals. Any setting other
+    /// than [`LiteralCase::Preserve`] also writes the exponent of float
+    /// literals as a lowercase `e`.
+    ///
+    /// Default: [`LiteralCase::Preserve`].
+    pub literal_case: LiteralCase,
+
+    /// Whether the type suffix of integer and float literals, as in `1u32`,
+    /// is separated from the digits by `_`.
+    ///
+    /// Default: [`LiteralSuffix::Preserve`].
+    pub literal_suffix: LiteralSuffix,
+
+    /// Write the value of a `const` as hexadecimal if it is a decimal integer
+    /// literal of at least 256 whose set bits are contiguous, such as
+    /// `4294967295` or `65280`, which are more recognizable as the bitmasks
+    /// `0xffffffff` and `0xff00`. Powers of two, which have a single bit set,
+    /// are left in decimal.
+    ///
+    /// Default: false.
+    pub hex_bitmask_consts: bool,
+
+    /// How to write the contents of string, byte string, C string, character
+    /// and byte literals. Any setting other than [`LiteralEscapes::Preserve`]
+    /// renders each literal from its value, escaping only what has to be
+    /// escaped, and writes a string as a raw string, as in `r#"{"id": 1}"#`,
+    /// where that is shorter.
+    ///
+    /// Default: [`LiteralEscapes::Preserve`].
+    pub literal_escapes: LiteralEscapes,
+}
+
+/// Characters used for leading indentation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IndentStyle {
+    /// Indent using spaces.
+    Spaces,
+    /// Indent using one tab per level of block indentation. For the purpose of
+    /// line width, each tab counts as [`indent_width`][Config::indent_width]
+    /// columns. Alignment narrower than a full indent is written as spaces.
+    Tabs,
+}
+
+/// How [`Config::imports_granularity`] merges `use` declarations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ImportsGranularity {
+    /// Keep the declarations as written.
+    Preserve,
+    /// Merge all imports from the same crate into one declaration, as in
+    /// `use std::{fmt, io::{self, Write}};`.
+    Crate,
+    /// Merge imports from the same module into one declaration, as in
+    /// `use std::io::{self, Write};`.
+    Module,
+}
+
+/// How [`Config::literal_case`] writes the letters of numeric literals.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LiteralCase {
+    /// Keep the letters as written.
+    Preserve,
+    /// Uppercase hexadecimal digits, as in `0xFF`.
+    Upper,
+    /// Lowercase hexadecimal digits, as in `0xff`.
+    Lower,
+}
+
+/// How [`Config::literal_suffix`] attaches type suffixes to numeric literals.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LiteralSuffix {
+    /// Keep the suffix as written.
+    Preserve,
+    /// Write the suffix directly after the digits, as in `255u8`.
+    Attached,
+    /// Separate the suffix from the digits with `_`, as in `255_u8`.
+    Separated,
+}
+
+/// How [`Config::literal_escapes`] writes string and character literals.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LiteralEscapes {
+    /// Keep the literals as written.
+    Preserve,
+    /// Escape every character other than printable ASCII, as in
+    /// `"caf\u{e9}"`.
+    Ascii,
+    /// Write printable non-ASCII characters as they are, as in `"café"`.
+    Unicode,
+}
+
+/// Function-like macros whose arguments are Rust syntax, for
+/// [`Config::macros`].
+///
+/// ```
+/// use prettyplease::{Config, MacroArgs};
+///
+/// let mut config = Config::default();
+/// config.macros.insert("anyhow::bail", MacroArgs::FormatArgs);
+/// config.macros.insert("my_crate::define_handlers", MacroArgs::Items);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MacroRegistry {
+    macros: Vec<(Vec<String>, MacroArgs)>,
+}
+
+/// The syntax of the arguments of a macro in a [`MacroRegistry`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MacroArgs {
+    /// Comma-separated expressions, as in `dbg!(a, b)`.
+    Exprs,
+    /// A format string followed by comma-separated arguments, some of which
+    /// may be named, as in `format!("{x}: {}", y, x = 1)`.
+    FormatArgs,
+    /// Zero or more items, as in `thread_local!`.
+    Items,
+    /// The statements of a block, optionally ending in an expression.
+    Block,
+}
+
+impl MacroRegistry {
+    /// Declares the syntax of the arguments of the macro at `path`, such as
+    /// `"serde_json::json"`, replacing any previous declaration for that path.
+    ///
+    /// Invocations match if their path is a suffix of the declared one, so
+    /// `anyhow::bail` applies to `anyhow::bail!(...)`, `::anyhow::bail!(...)`
+    /// and to `bail!(...)` after a `use anyhow::bail`. If several declared
+    /// paths match, the one inserted last is used.
+    pub fn insert(&mut self, path: &str, args: MacroArgs) {
+        let path: Vec<String> = path
+            .split("::")
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_owned)
+            .collect();
+        self.macros.retain(|(existing, _args)| *existing != path);
+        self.macros.push((path, args));
+    }
+
+    #[cfg(feature = "verbatim")]
+    pub(crate) fn get(&self, path: &syn::Path) -> Option<MacroArgs> {
+        let len = path.segments.len();
+        self.macros.iter().rev().find_map(|(declared, args)| {
+            let suffix = declared.get(declared.len().checked_sub(len)?..)?;
+            let matches = suffix
+                .iter()
+                .zip(&path.segments)
+                .all(|(declared, segment)| segment.ident == declared);
+            matches.then_some(*args)
+        })
+    }
+}
+
+// Target line width.
+const MARGIN: usize = 89;
+
+// Number of spaces increment at each level of block indentation.
+const INDENT: usize = 4;
+
+// Every line is allowed at least this much space, even if highly indented.
+const MIN_SPACE: usize = 60;
+
+// Struct literals wider than this are broken onto multiple lines.
+const STRUCT_LIT_WIDTH: usize = 34;
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_width: MARGIN,
+            indent_width: INDENT,
+            min_space: MIN_SPACE,
+            struct_lit_width: STRUCT_LIT_WIDTH,
+            indent_style: IndentStyle::Spaces,
+            raw_token_fallback: false,
+            preserve_blank_lines: false,
+            reorder_imports: false,
+            imports_granularity: ImportsGranularity::Preserve,
+            header: None,
+            macros: MacroRegistry::default(),
+            format_unknown_macros: false,
+            format_macro_rules: false,
+            wrap_doc_comments: false,
+            format_doc_code_blocks: false,
+            wrap_string_literals: false,
+            wrap_attribute_args: false,
+            digit_grouping: None,
+            literal_case: LiteralCase::Preserve,
+            literal_suffix: LiteralSuffix::Preserve,
+            hex_bitmask_consts: false,
+            literal_escapes: LiteralEscapes::Preserve,
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::algorithm::{self, BeginToken, BreakToken, Breaks, Printer};
 use std::borrow::Cow;
 
-impl Printer {
+impl Printer<'_> {
     pub fn ibox(&mut self, indent: isize) {
         self.scan_begin(BeginToken {
             offset: indent,
@@ -67,7 +67,7 @@ impl Printer {
     pub fn trailing_comma(&mut self, is_last: bool) {
         if is_last {
             self.scan_break(BreakToken {
-                pre_break: Some(','),
+                pre_break: (!self.exact_tokens).then_some(','),
                 ..BreakToken::default()
             });
         } else {
@@ -80,7 +80,7 @@ impl Printer {
         if is_last {
             self.scan_break(BreakToken {
                 blank_space: 1,
-                pre_break: Some(','),
+                pre_break: (!self.exact_tokens).then_some(','),
                 ..BreakToken::default()
             });
         } else {
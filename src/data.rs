@@ -2,29 +2,29 @@ use crate::algorithm::Printer;
 use crate::fixup::FixupContext;
 use crate::iter::IterDelimited;
 use crate::path::PathKind;
-use crate::INDENT;
 use syn::{Field, Fields, FieldsUnnamed, Variant, VisRestricted, Visibility};
 
-impl Printer {
+impl Printer<'_> {
     pub fn variant(&mut self, variant: &Variant) {
+        self.leading_comments(variant);
         self.outer_attrs(&variant.attrs);
         self.ident(&variant.ident);
         match &variant.fields {
             Fields::Named(fields) => {
                 self.nbsp();
                 self.word("{");
-                self.cbox(INDENT);
+                self.cbox(self.indent_unit);
                 self.space();
                 for field in fields.named.iter().delimited() {
                     self.field(&field);
                     self.trailing_comma_or_space(field.is_last);
                 }
-                self.offset(-INDENT);
+                self.offset(-self.indent_unit);
                 self.end();
                 self.word("}");
             }
             Fields::Unnamed(fields) => {
-                self.cbox(INDENT);
+                self.cbox(self.indent_unit);
                 self.fields_unnamed(fields);
                 self.end();
             }
@@ -43,11 +43,12 @@ impl Printer {
             self.field(&field);
             self.trailing_comma(field.is_last);
         }
-        self.offset(-INDENT);
+        self.offset(-self.indent_unit);
         self.word(")");
     }
 
     pub fn field(&mut self, field: &Field) {
+        self.leading_comments(field);
         self.outer_attrs(&field.attrs);
         self.visibility(&field.vis);
         if let Some(ident) = &field.ident {
@@ -0,0 +1,57 @@
+use crate::algorithm::Printer;
+use proc_macro2::TokenStream;
+use std::fmt::{self, Display};
+
+/// Error returned by [`try_unparse`][crate::try_unparse] when the syntax tree
+/// contains a node that cannot be printed.
+///
+/// This happens for `Verbatim` nodes whose tokens are not recognized syntax,
+/// and for `macro_rules!` invocations whose rules are malformed. Such nodes can
+/// instead be printed token by token using
+/// [`Config::raw_token_fallback`][crate::Config::raw_token_fallback].
+#[derive(Clone, Debug)]
+pub struct Error {
+    kind: &'static str,
+    tokens: String,
+}
+
+impl Error {
+    /// The kind of syntax tree node that could not be printed, for example
+    /// `"Item::Verbatim"`.
+    pub fn kind(&self) -> &str {
+        self.kind
+    }
+
+    /// The tokens of the node that could not be printed.
+    pub fn tokens(&self) -> &str {
+        &self.tokens
+    }
+
+    #[cold]
+    pub(crate) fn panic(self) -> ! {
+        unimplemented!("{} `{}`", self.kind, self.tokens);
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "unsupported {} `{}`", self.kind, self.tokens)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Printer<'_> {
+    pub fn unsupported(&mut self, kind: &'static str, tokens: &TokenStream) {
+        if self.config.raw_token_fallback {
+            self.ibox(0);
+            self.macro_rules_tokens(tokens.clone(), false);
+            self.end();
+        } else if self.error.is_none() {
+            self.error = Some(Error {
+                kind,
+                tokens: tokens.to_string(),
+            });
+        }
+    }
+}
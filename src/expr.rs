@@ -3,10 +3,12 @@ use crate::attr;
 use crate::classify;
 use crate::fixup::FixupContext;
 use crate::iter::IterDelimited;
+use crate::mac::MacroPosition;
 use crate::path::PathKind;
 use crate::precedence::Precedence;
+use crate::source_map::NodeKind;
 use crate::stmt;
-use crate::INDENT;
+use proc_macro2::extra::DelimSpan;
 use proc_macro2::TokenStream;
 use syn::punctuated::Punctuated;
 use syn::{
@@ -19,13 +21,14 @@ use syn::{
     RangeLimits, ReturnType, Stmt, Token, UnOp,
 };
 
-impl Printer {
+impl Printer<'_> {
     pub fn expr(&mut self, expr: &Expr, mut fixup: FixupContext) {
         let needs_paren = fixup.parenthesize(expr);
         if needs_paren {
             self.word("(");
             fixup = FixupContext::NONE;
         }
+        self.begin_node(NodeKind::Expr, expr);
 
         let beginning_of_line = false;
 
@@ -73,6 +76,7 @@ impl Printer {
             Expr::Yield(expr) => self.expr_yield(expr, fixup),
             _ => unimplemented!("unknown Expr"),
         }
+        self.end_node();
 
         if needs_paren {
             self.word(")");
@@ -92,6 +96,15 @@ impl Printer {
             fixup = FixupContext::NONE;
         }
 
+        // Any other expression is recorded in the source map by `expr`.
+        let is_chain = matches!(
+            expr,
+            Expr::Await(_) | Expr::Field(_) | Expr::Index(_) | Expr::MethodCall(_) | Expr::Try(_),
+        );
+        if is_chain {
+            self.begin_node(NodeKind::Expr, expr);
+        }
+
         match expr {
             Expr::Await(expr) => self.expr_await(expr, beginning_of_line, fixup),
             Expr::Field(expr) => self.expr_field(expr, beginning_of_line, fixup),
@@ -101,6 +114,9 @@ impl Printer {
             _ => self.expr(expr, fixup),
         }
 
+        if is_chain {
+            self.end_node();
+        }
         if needs_paren {
             self.word(")");
         }
@@ -119,6 +135,19 @@ impl Printer {
             fixup = FixupContext::NONE;
         }
 
+        let is_chain = matches!(
+            expr,
+            Expr::Await(_)
+                | Expr::Call(_)
+                | Expr::Field(_)
+                | Expr::Index(_)
+                | Expr::MethodCall(_)
+                | Expr::Try(_),
+        );
+        if is_chain {
+            self.begin_node(NodeKind::Expr, expr);
+        }
+
         match expr {
             Expr::Await(expr) => self.prefix_subexpr_await(expr, beginning_of_line, fixup),
             Expr::Call(expr) => self.prefix_subexpr_call(expr, fixup),
@@ -130,12 +159,15 @@ impl Printer {
             }
             Expr::Try(expr) => self.prefix_subexpr_try(expr, beginning_of_line, fixup),
             _ => {
-                self.cbox(-INDENT);
+                self.cbox(-self.indent_unit);
                 self.expr(expr, fixup);
                 self.end();
             }
         }
 
+        if is_chain {
+            self.end_node();
+        }
         if needs_paren {
             self.word(")");
         }
@@ -169,8 +201,8 @@ impl Printer {
         self.outer_attrs(&expr.attrs);
         if expr.elems.is_empty() {
             self.word("[]");
-        } else if simple_array(&expr.elems) {
-            self.cbox(INDENT);
+        } else if simple_array(&expr.elems) && !self.has_comments_within(expr.bracket_token.span) {
+            self.cbox(self.indent_unit);
             self.word("[");
             self.zerobreak();
             self.ibox(0);
@@ -183,18 +215,20 @@ impl Printer {
             }
             self.end();
             self.trailing_comma(true);
-            self.offset(-INDENT);
+            self.offset(-self.indent_unit);
             self.word("]");
             self.end();
         } else {
             self.word("[");
-            self.cbox(INDENT);
+            self.cbox(self.indent_unit);
             self.zerobreak();
             for elem in expr.elems.iter().delimited() {
+                self.leading_comments(*elem);
                 self.expr(&elem, FixupContext::NONE);
                 self.trailing_comma(elem.is_last);
             }
-            self.offset(-INDENT);
+            self.trailing_comments(expr.bracket_token.span);
+            self.offset(-self.indent_unit);
             self.end();
             self.word("]");
         }
@@ -230,14 +264,14 @@ impl Printer {
         if expr.capture.is_some() {
             self.word("move ");
         }
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.small_block(&expr.block, &expr.attrs);
         self.end();
     }
 
     fn expr_await(&mut self, expr: &ExprAwait, beginning_of_line: bool, fixup: FixupContext) {
         self.outer_attrs(&expr.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.prefix_subexpr_await(expr, beginning_of_line, fixup);
         self.end();
     }
@@ -256,7 +290,7 @@ impl Printer {
             beginning_of_line,
             left_fixup,
         );
-        if !(beginning_of_line && is_short_ident(&expr.base)) {
+        if !(beginning_of_line && is_short_ident(&expr.base, self.indent_unit)) {
             self.scan_break(BreakToken {
                 no_break: self.ends_with('.').then_some(' '),
                 ..BreakToken::default()
@@ -296,8 +330,8 @@ impl Printer {
             && right_fixup.rightmost_subexpression_precedence(&expr.right) <= binop_prec;
 
         self.outer_attrs(&expr.attrs);
-        self.ibox(INDENT);
-        self.ibox(-INDENT);
+        self.ibox(self.indent_unit);
+        self.ibox(-self.indent_unit);
         if !expr.attrs.is_empty() {
             self.word("(");
         }
@@ -318,7 +352,7 @@ impl Printer {
         if let Some(label) = &expr.label {
             self.label(label);
         }
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.small_block(&expr.block, &expr.attrs);
         self.end();
     }
@@ -356,7 +390,7 @@ impl Printer {
         self.outer_attrs(&expr.attrs);
         self.expr_beginning_of_line(&expr.func, needs_paren, beginning_of_line, left_fixup);
         self.word("(");
-        self.call_args(&expr.args);
+        self.call_args(&expr.args, expr.paren_token.span);
         self.word(")");
     }
 
@@ -376,7 +410,7 @@ impl Printer {
         let beginning_of_line = false;
         self.prefix_subexpr(&expr.func, needs_paren, beginning_of_line, left_fixup);
         self.word("(");
-        self.call_args(&expr.args);
+        self.call_args(&expr.args, expr.paren_token.span);
         self.word(")");
     }
 
@@ -385,8 +419,8 @@ impl Printer {
             fixup.leftmost_subexpression_with_operator(&expr.expr, false, false, Precedence::Cast);
 
         self.outer_attrs(&expr.attrs);
-        self.ibox(INDENT);
-        self.ibox(-INDENT);
+        self.ibox(self.indent_unit);
+        self.ibox(-self.indent_unit);
         if !expr.attrs.is_empty() {
             self.word("(");
         }
@@ -419,7 +453,7 @@ impl Printer {
         if expr.capture.is_some() {
             self.word("move ");
         }
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.word("|");
         for pat in expr.inputs.iter().delimited() {
             if pat.is_first {
@@ -435,7 +469,7 @@ impl Printer {
             ReturnType::Default => {
                 self.word("|");
                 self.space();
-                self.offset(-INDENT);
+                self.offset(-self.indent_unit);
                 self.end();
                 self.neverbreak();
                 let wrap_in_brace = match &*expr.body {
@@ -445,7 +479,7 @@ impl Printer {
                     body => !is_blocklike(body),
                 };
                 if wrap_in_brace {
-                    self.cbox(INDENT);
+                    self.cbox(self.indent_unit);
                     let okay_to_brace = parseable_as_stmt(&expr.body);
                     self.scan_break(BreakToken {
                         pre_break: Some(if okay_to_brace { '{' } else { '(' }),
@@ -456,7 +490,7 @@ impl Printer {
                         fixup.rightmost_subexpression_fixup(false, false, Precedence::Jump),
                     );
                     self.scan_break(BreakToken {
-                        offset: -INDENT,
+                        offset: -self.indent_unit,
                         pre_break: (okay_to_brace && stmt::add_semi(&expr.body)).then_some(';'),
                         post_break: if okay_to_brace { "}" } else { ")" },
                         ..BreakToken::default()
@@ -472,7 +506,7 @@ impl Printer {
             ReturnType::Type(_arrow, ty) => {
                 if !expr.inputs.is_empty() {
                     self.trailing_comma(true);
-                    self.offset(-INDENT);
+                    self.offset(-self.indent_unit);
                 }
                 self.word("|");
                 self.end();
@@ -487,7 +521,7 @@ impl Printer {
                         fixup.rightmost_subexpression_fixup(false, false, Precedence::Jump),
                     );
                 } else {
-                    self.cbox(INDENT);
+                    self.cbox(self.indent_unit);
                     self.expr_as_small_block(&expr.body, 0);
                     self.end();
                 }
@@ -499,7 +533,7 @@ impl Printer {
     pub fn expr_const(&mut self, expr: &ExprConst) {
         self.outer_attrs(&expr.attrs);
         self.word("const ");
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.small_block(&expr.block, &expr.attrs);
         self.end();
     }
@@ -515,7 +549,7 @@ impl Printer {
 
     fn expr_field(&mut self, expr: &ExprField, beginning_of_line: bool, fixup: FixupContext) {
         self.outer_attrs(&expr.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.prefix_subexpr_field(expr, beginning_of_line, fixup);
         self.end();
     }
@@ -534,7 +568,7 @@ impl Printer {
             beginning_of_line,
             left_fixup,
         );
-        if !(beginning_of_line && is_short_ident(&expr.base)) {
+        if !(beginning_of_line && is_short_ident(&expr.base, self.indent_unit)) {
             self.scan_break(BreakToken {
                 no_break: self.ends_with('.').then_some(' '),
                 ..BreakToken::default()
@@ -557,13 +591,11 @@ impl Printer {
         self.expr_condition(&expr.expr);
         self.word("{");
         self.neverbreak();
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.hardbreak_if_nonempty();
         self.inner_attrs(&expr.attrs);
-        for stmt in expr.body.stmts.iter().delimited() {
-            self.stmt(&stmt, stmt.is_last);
-        }
-        self.offset(-INDENT);
+        self.stmts(&expr.body);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
         self.end();
@@ -576,9 +608,9 @@ impl Printer {
 
     fn expr_if(&mut self, expr: &ExprIf) {
         self.outer_attrs(&expr.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.word("if ");
-        self.cbox(-INDENT);
+        self.cbox(-self.indent_unit);
         self.expr_condition(&expr.cond);
         self.end();
         if let Some((_else_token, else_branch)) = &expr.else_branch {
@@ -589,7 +621,7 @@ impl Printer {
                 match else_branch {
                     Expr::If(expr) => {
                         self.word("if ");
-                        self.cbox(-INDENT);
+                        self.cbox(-self.indent_unit);
                         self.expr_condition(&expr.cond);
                         self.end();
                         self.small_block(&expr.then_branch, &[]);
@@ -603,7 +635,7 @@ impl Printer {
                     }
                     // If not one of the valid expressions to exist in an else
                     // clause, wrap in a block.
-                    other => self.expr_as_small_block(other, INDENT),
+                    other => self.expr_as_small_block(other, self.indent_unit),
                 }
                 break;
             }
@@ -612,10 +644,8 @@ impl Printer {
         } else {
             self.word("{");
             self.hardbreak();
-            for stmt in expr.then_branch.stmts.iter().delimited() {
-                self.stmt(&stmt, stmt.is_last);
-            }
-            self.offset(-INDENT);
+            self.stmts(&expr.then_branch);
+            self.offset(-self.indent_unit);
             self.word("}");
         }
         self.end();
@@ -698,13 +728,11 @@ impl Printer {
             self.label(label);
         }
         self.word("loop {");
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.hardbreak_if_nonempty();
         self.inner_attrs(&expr.attrs);
-        for stmt in expr.body.stmts.iter().delimited() {
-            self.stmt(&stmt, stmt.is_last);
-        }
-        self.offset(-INDENT);
+        self.stmts(&expr.body);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
     }
@@ -712,7 +740,7 @@ impl Printer {
     pub fn expr_macro(&mut self, expr: &ExprMacro) {
         self.outer_attrs(&expr.attrs);
         let semicolon = false;
-        self.mac(&expr.mac, None, semicolon);
+        self.mac(&expr.mac, None, semicolon, MacroPosition::Expr);
     }
 
     fn expr_match(&mut self, expr: &ExprMatch) {
@@ -722,14 +750,15 @@ impl Printer {
         self.expr_condition(&expr.expr);
         self.word("{");
         self.neverbreak();
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.hardbreak_if_nonempty();
         self.inner_attrs(&expr.attrs);
         for arm in &expr.arms {
             self.arm(arm);
             self.hardbreak();
         }
-        self.offset(-INDENT);
+        self.trailing_comments(expr.brace_token.span);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
         self.end();
@@ -742,8 +771,9 @@ impl Printer {
         fixup: FixupContext,
     ) {
         self.outer_attrs(&expr.attrs);
-        self.cbox(INDENT);
-        let unindent_call_args = beginning_of_line && is_short_ident(&expr.receiver);
+        self.cbox(self.indent_unit);
+        let unindent_call_args =
+            beginning_of_line && is_short_ident(&expr.receiver, self.indent_unit);
         self.prefix_subexpr_method_call(expr, beginning_of_line, unindent_call_args, fixup);
         self.end();
     }
@@ -763,7 +793,7 @@ impl Printer {
             beginning_of_line,
             left_fixup,
         );
-        if !(beginning_of_line && is_short_ident(&expr.receiver)) {
+        if !(beginning_of_line && is_short_ident(&expr.receiver, self.indent_unit)) {
             self.scan_break(BreakToken {
                 no_break: self.ends_with('.').then_some(' '),
                 ..BreakToken::default()
@@ -774,9 +804,13 @@ impl Printer {
         if let Some(turbofish) = &expr.turbofish {
             self.angle_bracketed_generic_arguments(turbofish, PathKind::Expr);
         }
-        self.cbox(if unindent_call_args { -INDENT } else { 0 });
+        self.cbox(if unindent_call_args {
+            -self.indent_unit
+        } else {
+            0
+        });
         self.word("(");
-        self.call_args(&expr.args);
+        self.call_args(&expr.args, expr.paren_token.span);
         self.word(")");
         self.end();
     }
@@ -865,23 +899,26 @@ impl Printer {
 
     fn expr_struct(&mut self, expr: &ExprStruct) {
         self.outer_attrs(&expr.attrs);
-        self.cbox(INDENT);
-        self.ibox(-INDENT);
+        self.cbox(self.indent_unit);
+        self.ibox(-self.indent_unit);
         self.qpath(&expr.qself, &expr.path, PathKind::Expr);
         self.end();
         self.word(" {");
         self.space_if_nonempty();
         for field_value in expr.fields.iter().delimited() {
+            self.leading_comments(*field_value);
             self.field_value(&field_value);
             self.trailing_comma_or_space(field_value.is_last && expr.rest.is_none());
         }
         if let Some(rest) = &expr.rest {
+            self.leading_comments(rest);
             self.word("..");
             self.expr(rest, FixupContext::NONE);
             self.space();
         }
-        self.offset(-INDENT);
-        self.end_with_max_width(34);
+        self.trailing_comments(expr.brace_token.span);
+        self.offset(-self.indent_unit);
+        self.end_with_max_width(self.config.struct_lit_width as isize);
         self.word("}");
     }
 
@@ -913,7 +950,7 @@ impl Printer {
     fn expr_try_block(&mut self, expr: &ExprTryBlock) {
         self.outer_attrs(&expr.attrs);
         self.word("try ");
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.small_block(&expr.block, &expr.attrs);
         self.end();
     }
@@ -921,7 +958,7 @@ impl Printer {
     fn expr_tuple(&mut self, expr: &ExprTuple) {
         self.outer_attrs(&expr.attrs);
         self.word("(");
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.zerobreak();
         for elem in expr.elems.iter().delimited() {
             self.expr(&elem, FixupContext::NONE);
@@ -932,7 +969,7 @@ impl Printer {
                 self.trailing_comma(elem.is_last);
             }
         }
-        self.offset(-INDENT);
+        self.offset(-self.indent_unit);
         self.end();
         self.word(")");
     }
@@ -949,7 +986,7 @@ impl Printer {
     fn expr_unsafe(&mut self, expr: &ExprUnsafe) {
         self.outer_attrs(&expr.attrs);
         self.word("unsafe ");
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.small_block(&expr.block, &expr.attrs);
         self.end();
     }
@@ -957,7 +994,7 @@ impl Printer {
     #[cfg(not(feature = "verbatim"))]
     fn expr_verbatim(&mut self, expr: &TokenStream, _fixup: FixupContext) {
         if !expr.is_empty() {
-            unimplemented!("Expr::Verbatim `{}`", expr);
+            self.unsupported("Expr::Verbatim", expr);
         }
     }
 
@@ -1022,7 +1059,7 @@ impl Printer {
 
         let expr: ExprVerbatim = match syn::parse2(tokens.clone()) {
             Ok(expr) => expr,
-            Err(_) => unimplemented!("Expr::Verbatim `{}`", tokens),
+            Err(_) => return self.unsupported("Expr::Verbatim", tokens),
         };
 
         match expr {
@@ -1045,13 +1082,13 @@ impl Printer {
                 self.ident(&expr.name);
                 self.word("(");
                 if !expr.args.is_empty() {
-                    self.cbox(INDENT);
+                    self.cbox(self.indent_unit);
                     self.zerobreak();
                     self.ibox(0);
                     self.macro_rules_tokens(expr.args, false);
                     self.end();
                     self.zerobreak();
-                    self.offset(-INDENT);
+                    self.offset(-self.indent_unit);
                     self.end();
                 }
                 self.word(")");
@@ -1068,13 +1105,11 @@ impl Printer {
         self.expr_condition(&expr.cond);
         self.word("{");
         self.neverbreak();
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.hardbreak_if_nonempty();
         self.inner_attrs(&expr.attrs);
-        for stmt in expr.body.stmts.iter().delimited() {
-            self.stmt(&stmt, stmt.is_last);
-        }
-        self.offset(-INDENT);
+        self.stmts(&expr.body);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
     }
@@ -1108,6 +1143,7 @@ impl Printer {
     }
 
     fn arm(&mut self, arm: &Arm) {
+        self.leading_comments(arm);
         self.outer_attrs(&arm.attrs);
         self.ibox(0);
         self.pat(&arm.pat);
@@ -1147,18 +1183,16 @@ impl Printer {
             }
             self.word("{");
             self.neverbreak();
-            self.cbox(INDENT);
+            self.cbox(self.indent_unit);
             self.hardbreak_if_nonempty();
             self.inner_attrs(&body.attrs);
-            for stmt in body.block.stmts.iter().delimited() {
-                self.stmt(&stmt, stmt.is_last);
-            }
-            self.offset(-INDENT);
+            self.stmts(&body.block);
+            self.offset(-self.indent_unit);
             self.end();
             self.word("}");
         } else {
             self.neverbreak();
-            self.cbox(INDENT);
+            self.cbox(self.indent_unit);
             let okay_to_brace = parseable_as_stmt(body);
             self.scan_break(BreakToken {
                 pre_break: Some(if okay_to_brace { '{' } else { '(' }),
@@ -1166,7 +1200,7 @@ impl Printer {
             });
             self.expr_beginning_of_line(body, false, true, FixupContext::new_match_arm());
             self.scan_break(BreakToken {
-                offset: -INDENT,
+                offset: -self.indent_unit,
                 pre_break: (okay_to_brace && stmt::add_semi(body)).then_some(';'),
                 post_break: if okay_to_brace { "}" } else { ")," },
                 no_break: classify::requires_comma_to_be_match_arm(body).then_some(','),
@@ -1177,20 +1211,22 @@ impl Printer {
         self.end();
     }
 
-    fn call_args(&mut self, args: &Punctuated<Expr, Token![,]>) {
+    fn call_args(&mut self, args: &Punctuated<Expr, Token![,]>, paren: DelimSpan) {
         let mut iter = args.iter();
         match (iter.next(), iter.next()) {
-            (Some(expr), None) if is_blocklike(expr) => {
+            (Some(expr), None) if is_blocklike(expr) && !self.has_comments_within(paren) => {
                 self.expr(expr, FixupContext::NONE);
             }
             _ => {
-                self.cbox(INDENT);
+                self.cbox(self.indent_unit);
                 self.zerobreak();
                 for arg in args.iter().delimited() {
+                    self.leading_comments(*arg);
                     self.expr(&arg, FixupContext::NONE);
                     self.trailing_comma(arg.is_last);
                 }
-                self.offset(-INDENT);
+                self.trailing_comments(paren);
+                self.offset(-self.indent_unit);
                 self.end();
             }
         }
@@ -1202,19 +1238,20 @@ impl Printer {
             self.space();
             self.inner_attrs(attrs);
             match block.stmts.as_slice() {
-                [Stmt::Expr(expr, None)] if stmt::break_after(expr) => {
+                [Stmt::Expr(expr, None)]
+                    if stmt::break_after(expr)
+                        && !self.has_comments_within(block.brace_token.span) =>
+                {
                     self.ibox(0);
                     self.expr_beginning_of_line(expr, false, true, FixupContext::new_stmt());
                     self.end();
                     self.space();
                 }
                 _ => {
-                    for stmt in block.stmts.iter().delimited() {
-                        self.stmt(&stmt, stmt.is_last);
-                    }
+                    self.stmts(block);
                 }
             }
-            self.offset(-INDENT);
+            self.offset(-self.indent_unit);
         }
         self.word("}");
     }
@@ -1222,11 +1259,12 @@ impl Printer {
     pub fn expr_as_small_block(&mut self, expr: &Expr, indent: isize) {
         self.word("{");
         self.space();
+        self.leading_comments(expr);
         self.ibox(indent);
         self.expr_beginning_of_line(expr, false, true, FixupContext::new_stmt());
         self.end();
         self.space();
-        self.offset(-INDENT);
+        self.offset(-self.indent_unit);
         self.word("}");
     }
 
@@ -1352,14 +1390,14 @@ fn needs_newline_if_wrap(expr: &Expr) -> bool {
     }
 }
 
-fn is_short_ident(expr: &Expr) -> bool {
+fn is_short_ident(expr: &Expr, indent: isize) -> bool {
     if let Expr::Path(expr) = expr {
         return expr.attrs.is_empty()
             && expr.qself.is_none()
             && expr
                 .path
                 .get_ident()
-                .is_some_and(|ident| ident.to_string().len() as isize <= INDENT);
+                .is_some_and(|ident| ident.to_string().len() as isize <= indent);
     }
     false
 }
@@ -1,17 +1,22 @@
 use crate::algorithm::Printer;
 use syn::File;
 
-impl Printer {
+impl Printer<'_> {
     pub fn file(&mut self, file: &File) {
         self.cbox(0);
         if let Some(shebang) = &file.shebang {
             self.word(shebang.clone());
             self.hardbreak();
         }
-        self.inner_attrs(&file.attrs);
-        for item in &file.items {
-            self.item(item);
+        if let Some(header) = self.config.header.clone() {
+            for line in header.lines() {
+                self.word(line.trim_end().to_owned());
+                self.hardbreak();
+            }
         }
+        self.inner_attrs(&file.attrs);
+        self.items(&file.items);
+        self.remaining_comments();
         self.end();
     }
 }
@@ -1,7 +1,6 @@
 use crate::algorithm::Printer;
 use crate::iter::IterDelimited;
 use crate::path::PathKind;
-use crate::INDENT;
 use proc_macro2::TokenStream;
 use std::ptr;
 use syn::{
@@ -10,7 +9,7 @@ use syn::{
     TypeParamBound, WhereClause, WherePredicate,
 };
 
-impl Printer {
+impl Printer<'_> {
     pub fn generics(&mut self, generics: &Generics) {
         if generics.params.is_empty() {
             return;
@@ -43,7 +42,7 @@ impl Printer {
             }
         }
 
-        self.offset(-INDENT);
+        self.offset(-self.indent_unit);
         self.end();
         self.word(">");
     }
@@ -83,7 +82,7 @@ impl Printer {
     fn type_param(&mut self, type_param: &TypeParam) {
         self.outer_attrs(&type_param.attrs);
         self.ident(&type_param.ident);
-        self.ibox(INDENT);
+        self.ibox(self.indent_unit);
         for type_param_bound in type_param.bounds.iter().delimited() {
             if type_param_bound.is_first {
                 self.word(": ");
@@ -151,7 +150,7 @@ impl Printer {
 
     #[cfg(not(feature = "verbatim"))]
     fn type_param_bound_verbatim(&mut self, bound: &TokenStream) {
-        unimplemented!("TypeParamBound::Verbatim `{}`", bound);
+        self.unsupported("TypeParamBound::Verbatim", bound);
     }
 
     #[cfg(feature = "verbatim")]
@@ -223,7 +222,7 @@ impl Printer {
 
         let bound: TypeParamBoundVerbatim = match syn::parse2(tokens.clone()) {
             Ok(bound) => bound,
-            Err(_) => unimplemented!("TypeParamBound::Verbatim `{}`", tokens),
+            Err(_) => return self.unsupported("TypeParamBound::Verbatim", tokens),
         };
 
         match bound {
@@ -291,7 +290,7 @@ impl Printer {
         };
         if hardbreaks {
             self.hardbreak();
-            self.offset(-INDENT);
+            self.offset(-self.indent_unit);
             self.word("where");
             self.hardbreak();
             for predicate in where_clause.predicates.iter().delimited() {
@@ -304,11 +303,11 @@ impl Printer {
                 }
             }
             if !semi {
-                self.offset(-INDENT);
+                self.offset(-self.indent_unit);
             }
         } else {
             self.space();
-            self.offset(-INDENT);
+            self.offset(-self.indent_unit);
             self.word("where");
             self.space();
             for predicate in where_clause.predicates.iter().delimited() {
@@ -320,7 +319,7 @@ impl Printer {
                 }
             }
             if !semi {
-                self.offset(-INDENT);
+                self.offset(-self.indent_unit);
             }
         }
     }
@@ -343,7 +342,7 @@ impl Printer {
         if predicate.bounds.len() == 1 {
             self.ibox(0);
         } else {
-            self.ibox(INDENT);
+            self.ibox(self.indent_unit);
         }
         for type_param_bound in predicate.bounds.iter().delimited() {
             if type_param_bound.is_first {
@@ -360,7 +359,7 @@ impl Printer {
     fn predicate_lifetime(&mut self, predicate: &PredicateLifetime) {
         self.lifetime(&predicate.lifetime);
         self.word(":");
-        self.ibox(INDENT);
+        self.ibox(self.indent_unit);
         for lifetime in predicate.bounds.iter().delimited() {
             if lifetime.is_first {
                 self.nbsp();
@@ -409,7 +408,7 @@ impl Printer {
             Expr::Block(expr) => self.expr_block(expr),
 
             _ => {
-                self.cbox(INDENT);
+                self.cbox(self.indent_unit);
                 self.expr_as_small_block(expr, 0);
                 self.end();
             }
@@ -0,0 +1,417 @@
+use crate::config::{Config, ImportsGranularity};
+use proc_macro2::Ident;
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::mem;
+use std::str::Chars;
+use syn::punctuated::Punctuated;
+use syn::{token, ItemUse, UseGlob, UseGroup, UseName, UsePath, UseRename, UseTree, Visibility};
+
+pub fn enabled(config: &Config) -> bool {
+    config.reorder_imports || config.imports_granularity != ImportsGranularity::Preserve
+}
+
+// A use declaration to print: the attributes, visibility and leading colon of
+// `item`, with `tree` in place of the original tree.
+pub struct Import<'a> {
+    pub item: &'a ItemUse,
+    pub tree: UseTree,
+}
+
+// Rewrites a run of consecutive use declarations according to the config's
+// import options. Declarations carrying attributes are never merged or
+// deduplicated, only sorted.
+pub fn normalize<'a>(uses: &[&'a ItemUse], config: &Config) -> Vec<Import<'a>> {
+    let mut imports = match config.imports_granularity {
+        ImportsGranularity::Preserve => uses
+            .iter()
+            .map(|&item| Import {
+                item,
+                tree: clone_tree(&item.tree),
+            })
+            .collect(),
+        granularity => merge(uses, granularity),
+    };
+    if config.reorder_imports {
+        for import in &mut imports {
+            sort_tree(&mut import.tree);
+        }
+        // Declarations with the same tree are ordered by visibility, so that
+        // duplicates are next to each other.
+        imports.sort_by(|a, b| {
+            cmp_tree(&a.tree, &b.tree).then_with(|| cmp_declaration(a.item, b.item))
+        });
+        imports.dedup_by(|b, a| {
+            mergeable(a.item, b.item) && cmp_tree(&a.tree, &b.tree) == Ordering::Equal
+        });
+    }
+    imports
+}
+
+enum Entry<'a> {
+    Single(Import<'a>),
+    Merged {
+        item: &'a ItemUse,
+        key: Vec<Ident>,
+        trie: Trie,
+    },
+}
+
+fn merge<'a>(uses: &[&'a ItemUse], granularity: ImportsGranularity) -> Vec<Import<'a>> {
+    let mut entries = Vec::new();
+    for &item in uses {
+        if !item.attrs.is_empty() {
+            entries.push(Entry::Single(Import {
+                item,
+                tree: clone_tree(&item.tree),
+            }));
+            continue;
+        }
+        let mut paths = Vec::new();
+        flatten(&item.tree, &mut Vec::new(), &mut paths);
+        for (prefix, leaf) in paths {
+            let key = match granularity {
+                ImportsGranularity::Crate => match (prefix.first(), &leaf) {
+                    (Some(first), _) => vec![first.clone()],
+                    (None, UseTree::Name(name)) => vec![name.ident.clone()],
+                    (None, UseTree::Rename(rename)) => vec![rename.ident.clone()],
+                    (None, _) => Vec::new(),
+                },
+                _ => prefix.clone(),
+            };
+            if key.is_empty() {
+                entries.push(Entry::Single(Import { item, tree: leaf }));
+                continue;
+            }
+            let existing = entries.iter_mut().find_map(|entry| match entry {
+                Entry::Merged {
+                    item: existing_item,
+                    key: existing_key,
+                    trie,
+                } if *existing_key == key && mergeable(existing_item, item) => Some(trie),
+                _ => None,
+            });
+            if let Some(trie) = existing {
+                trie.insert(&prefix, leaf);
+            } else {
+                let mut trie = Trie::default();
+                trie.insert(&prefix, leaf);
+                entries.push(Entry::Merged { item, key, trie });
+            }
+        }
+    }
+
+    let mut imports = Vec::new();
+    for entry in entries {
+        match entry {
+            Entry::Single(import) => imports.push(import),
+            Entry::Merged { item, trie, .. } => {
+                for tree in trie.render() {
+                    imports.push(Import { item, tree });
+                }
+            }
+        }
+    }
+    imports
+}
+
+// Splits a use tree into its individual imports, each a module path followed
+// by a name, rename, or glob.
+fn flatten(tree: &UseTree, prefix: &mut Vec<Ident>, out: &mut Vec<(Vec<Ident>, UseTree)>) {
+    match tree {
+        UseTree::Path(path) => {
+            prefix.push(path.ident.clone());
+            flatten(&path.tree, prefix, out);
+            prefix.pop();
+        }
+        UseTree::Name(_) | UseTree::Rename(_) | UseTree::Glob(_) => {
+            out.push((prefix.clone(), clone_tree(tree)));
+        }
+        UseTree::Group(group) => {
+            for tree in &group.items {
+                flatten(tree, prefix, out);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Trie {
+    children: Vec<(Ident, Trie)>,
+    leaves: Vec<UseTree>,
+}
+
+impl Trie {
+    fn insert(&mut self, path: &[Ident], leaf: UseTree) {
+        let Some((first, rest)) = path.split_first() else {
+            self.leaves.push(leaf);
+            return;
+        };
+        let position = self.children.iter().position(|(ident, _)| ident == first);
+        let i = position.unwrap_or_else(|| {
+            self.children.push((first.clone(), Trie::default()));
+            self.children.len() - 1
+        });
+        self.children[i].1.insert(rest, leaf);
+    }
+
+    fn render(mut self) -> Vec<UseTree> {
+        let mut items = Vec::new();
+        for (ident, mut child) in self.children {
+            // `use a;` merged with `use a::b;` becomes `use a::{self, b};`.
+            let len = self.leaves.len();
+            self.leaves
+                .retain(|leaf| !matches!(leaf, UseTree::Name(name) if name.ident == ident));
+            if self.leaves.len() < len {
+                child.leaves.push(UseTree::Name(UseName {
+                    ident: Ident::new("self", ident.span()),
+                }));
+            }
+            let mut trees = child.render();
+            // A lone `self` stays in braces: `use a::self;` is not valid.
+            let tree = if trees.len() == 1 && !is_self(&trees[0]) {
+                trees.pop().unwrap()
+            } else {
+                UseTree::Group(UseGroup {
+                    brace_token: token::Brace::default(),
+                    items: Punctuated::from_iter(trees),
+                })
+            };
+            items.push(UseTree::Path(UsePath {
+                ident,
+                colon2_token: token::PathSep::default(),
+                tree: Box::new(tree),
+            }));
+        }
+        items.append(&mut self.leaves);
+        sort_trees(&mut items);
+        items
+    }
+}
+
+fn sort_tree(tree: &mut UseTree) {
+    match tree {
+        UseTree::Path(path) => sort_tree(&mut path.tree),
+        UseTree::Name(_) | UseTree::Rename(_) | UseTree::Glob(_) => {}
+        UseTree::Group(group) => {
+            let mut items: Vec<UseTree> = mem::take(&mut group.items).into_iter().collect();
+            for tree in &mut items {
+                sort_tree(tree);
+            }
+            sort_trees(&mut items);
+            group.items = Punctuated::from_iter(items);
+        }
+    }
+}
+
+// Syntax tree nodes do not implement Clone unless syn's "clone-impls" feature
+// is enabled, which this crate does not otherwise need.
+fn clone_tree(tree: &UseTree) -> UseTree {
+    match tree {
+        UseTree::Path(path) => UseTree::Path(UsePath {
+            ident: path.ident.clone(),
+            colon2_token: token::PathSep::default(),
+            tree: Box::new(clone_tree(&path.tree)),
+        }),
+        UseTree::Name(name) => UseTree::Name(UseName {
+            ident: name.ident.clone(),
+        }),
+        UseTree::Rename(rename) => UseTree::Rename(UseRename {
+            ident: rename.ident.clone(),
+            as_token: token::As::default(),
+            rename: rename.rename.clone(),
+        }),
+        UseTree::Glob(_) => UseTree::Glob(UseGlob {
+            star_token: token::Star::default(),
+        }),
+        UseTree::Group(group) => UseTree::Group(UseGroup {
+            brace_token: token::Brace::default(),
+            items: group.items.iter().map(clone_tree).collect(),
+        }),
+    }
+}
+
+fn is_self(tree: &UseTree) -> bool {
+    match tree {
+        UseTree::Name(name) => name.ident == "self",
+        UseTree::Rename(rename) => rename.ident == "self",
+        UseTree::Path(_) | UseTree::Glob(_) | UseTree::Group(_) => false,
+    }
+}
+
+fn sort_trees(trees: &mut Vec<UseTree>) {
+    trees.sort_by(cmp_tree);
+    trees.dedup_by(|b, a| cmp_tree(a, b) == Ordering::Equal);
+}
+
+// Whether two use declarations differ at most in their tree.
+fn mergeable(a: &ItemUse, b: &ItemUse) -> bool {
+    a.attrs.is_empty()
+        && b.attrs.is_empty()
+        && a.leading_colon.is_some() == b.leading_colon.is_some()
+        && same_visibility(&a.vis, &b.vis)
+}
+
+// Orders use declarations so that those `mergeable` considers the same are
+// next to each other: without attributes first, then by visibility and
+// leading colon.
+fn cmp_declaration(a: &ItemUse, b: &ItemUse) -> Ordering {
+    fn key(item: &ItemUse) -> (bool, u8, Vec<String>, bool) {
+        let mut path = Vec::new();
+        let rank = match &item.vis {
+            Visibility::Public(_) => 0,
+            Visibility::Restricted(vis) => {
+                if vis.in_token.is_some() {
+                    path.push("in".to_owned());
+                }
+                if vis.path.leading_colon.is_some() {
+                    path.push("::".to_owned());
+                }
+                for segment in &vis.path.segments {
+                    path.push(segment.ident.to_string());
+                }
+                1
+            }
+            Visibility::Inherited => 2,
+        };
+        let attrs = !item.attrs.is_empty();
+        (attrs, rank, path, item.leading_colon.is_some())
+    }
+
+    key(a).cmp(&key(b))
+}
+
+fn same_visibility(a: &Visibility, b: &Visibility) -> bool {
+    match (a, b) {
+        (Visibility::Public(_), Visibility::Public(_))
+        | (Visibility::Inherited, Visibility::Inherited) => true,
+        (Visibility::Restricted(a), Visibility::Restricted(b)) => {
+            a.in_token.is_some() == b.in_token.is_some()
+                && a.path.leading_colon.is_some() == b.path.leading_colon.is_some()
+                && a.path.segments.len() == b.path.segments.len()
+                && a.path
+                    .segments
+                    .iter()
+                    .zip(&b.path.segments)
+                    .all(|(a, b)| a.ident == b.ident)
+        }
+        _ => false,
+    }
+}
+
+// Ordering of rustfmt's 2024 style edition: paths are compared segment by
+// segment, with `self`, `super` and `crate` ahead of other names, names in
+// version-sort order, then globs, then nested groups. A path sorts before any
+// longer path it is a prefix of, and a plain name before its renames.
+fn cmp_tree(a: &UseTree, b: &UseTree) -> Ordering {
+    fn segment(tree: &UseTree) -> (u8, Option<&Ident>, Option<&Ident>) {
+        match tree {
+            UseTree::Path(path) => (0, Some(&path.ident), None),
+            UseTree::Name(name) => (0, Some(&name.ident), None),
+            UseTree::Rename(rename) => (0, Some(&rename.ident), Some(&rename.rename)),
+            UseTree::Glob(_) => (1, None, None),
+            UseTree::Group(_) => (2, None, None),
+        }
+    }
+
+    let (a_kind, a_ident, a_rename) = segment(a);
+    let (b_kind, b_ident, b_rename) = segment(b);
+    let ordering = a_kind
+        .cmp(&b_kind)
+        .then_with(|| cmp_option(a_ident, b_ident, cmp_ident))
+        .then_with(|| cmp_option(a_rename, b_rename, cmp_ident));
+    if ordering != Ordering::Equal {
+        return ordering;
+    }
+    match (a, b) {
+        (UseTree::Path(a), UseTree::Path(b)) => cmp_tree(&a.tree, &b.tree),
+        (UseTree::Path(_), _) => Ordering::Greater,
+        (_, UseTree::Path(_)) => Ordering::Less,
+        (UseTree::Group(a), UseTree::Group(b)) => {
+            for (a, b) in a.items.iter().zip(&b.items) {
+                let ordering = cmp_tree(a, b);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            a.items.len().cmp(&b.items.len())
+        }
+        _ => Ordering::Equal,
+    }
+}
+
+fn cmp_option<T>(a: Option<T>, b: Option<T>, cmp: fn(T, T) -> Ordering) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (Some(a), Some(b)) => cmp(a, b),
+    }
+}
+
+fn cmp_ident(a: &Ident, b: &Ident) -> Ordering {
+    fn rank(ident: &str) -> u8 {
+        match ident {
+            "self" => 0,
+            "super" => 1,
+            "crate" => 2,
+            _ => 3,
+        }
+    }
+
+    let a = a.to_string();
+    let b = b.to_string();
+    let a = a.strip_prefix("r#").unwrap_or(&a);
+    let b = b.strip_prefix("r#").unwrap_or(&b);
+    rank(a).cmp(&rank(b)).then_with(|| version_cmp(a, b))
+}
+
+// Compares character by character, except that runs of digits are compared by
+// numeric value. Among equal numbers, more leading zeros sort first.
+fn version_cmp(a: &str, b: &str) -> Ordering {
+    fn digits(chars: &mut Peekable<Chars>) -> String {
+        let mut digits = String::new();
+        while let Some(&ch) = chars.peek() {
+            if !ch.is_ascii_digit() {
+                break;
+            }
+            digits.push(ch);
+            chars.next();
+        }
+        digits
+    }
+
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    let mut zeros = Ordering::Equal;
+    loop {
+        match (a.peek(), b.peek()) {
+            (None, None) => return zeros,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = digits(&mut a);
+                let y = digits(&mut b);
+                let x_value = x.trim_start_matches('0');
+                let y_value = y.trim_start_matches('0');
+                let ordering = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                if zeros == Ordering::Equal {
+                    zeros = y.len().cmp(&x.len());
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
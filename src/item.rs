@@ -1,21 +1,24 @@
 use crate::algorithm::Printer;
 use crate::fixup::FixupContext;
+use crate::imports;
 use crate::iter::IterDelimited;
-use crate::mac;
+use crate::mac::{self, MacroPosition};
 use crate::path::PathKind;
-use crate::INDENT;
+use crate::source_map::NodeKind;
 use proc_macro2::TokenStream;
 use syn::{
-    Fields, FnArg, ForeignItem, ForeignItemFn, ForeignItemMacro, ForeignItemStatic,
+    Expr, ExprLit, Fields, FnArg, ForeignItem, ForeignItemFn, ForeignItemMacro, ForeignItemStatic,
     ForeignItemType, ImplItem, ImplItemConst, ImplItemFn, ImplItemMacro, ImplItemType, Item,
     ItemConst, ItemEnum, ItemExternCrate, ItemFn, ItemForeignMod, ItemImpl, ItemMacro, ItemMod,
-    ItemStatic, ItemStruct, ItemTrait, ItemTraitAlias, ItemType, ItemUnion, ItemUse, Receiver,
+    ItemStatic, ItemStruct, ItemTrait, ItemTraitAlias, ItemType, ItemUnion, ItemUse, Lit, Receiver,
     Signature, StaticMutability, TraitItem, TraitItemConst, TraitItemFn, TraitItemMacro,
     TraitItemType, Type, UseGlob, UseGroup, UseName, UsePath, UseRename, UseTree, Variadic,
 };
 
-impl Printer {
+impl Printer<'_> {
     pub fn item(&mut self, item: &Item) {
+        self.leading_comments(item);
+        self.begin_node(NodeKind::Item, item);
         match item {
             #![cfg_attr(all(test, exhaustive), deny(non_exhaustive_omitted_patterns))]
             Item::Const(item) => self.item_const(item),
@@ -36,6 +39,39 @@ impl Printer {
             Item::Verbatim(item) => self.item_verbatim(item),
             _ => unimplemented!("unknown Item"),
         }
+        self.end_node();
+    }
+
+    pub fn items(&mut self, items: &[Item]) {
+        let mut prev = None;
+        let mut rest = items;
+        while let Some(item) = rest.first() {
+            if let Some(prev) = prev {
+                self.blank_line_between(prev, item);
+            }
+            let uses: Vec<&ItemUse> = if imports::enabled(&self.config) {
+                rest.iter()
+                    .map_while(|item| match item {
+                        Item::Use(item) => Some(item),
+                        _ => None,
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let len = if uses.is_empty() {
+                self.item(item);
+                1
+            } else {
+                self.leading_comments(item);
+                for import in imports::normalize(&uses, &self.config) {
+                    self.item_use_with_tree(import.item, &import.tree);
+                }
+                uses.len()
+            };
+            prev = Some(&rest[len - 1]);
+            rest = &rest[len..];
+        }
     }
 
     fn item_const(&mut self, item: &ItemConst) {
@@ -49,15 +85,33 @@ impl Printer {
         self.ty(&item.ty);
         self.word(" = ");
         self.neverbreak();
-        self.expr(&item.expr, FixupContext::NONE);
+        self.const_value(&item.expr);
         self.word(";");
         self.end();
         self.hardbreak();
     }
 
+    fn const_value(&mut self, expr: &Expr) {
+        if let Expr::Lit(ExprLit {
+            attrs,
+            lit: Lit::Int(lit),
+        }) = expr
+        {
+            if self.config.hex_bitmask_consts && attrs.is_empty() {
+                if let Some(hex) = self.bitmask_hex(lit) {
+                    self.begin_node(NodeKind::Expr, expr);
+                    self.word(hex);
+                    self.end_node();
+                    return;
+                }
+            }
+        }
+        self.expr(expr, FixupContext::NONE);
+    }
+
     fn item_enum(&mut self, item: &ItemEnum) {
         self.outer_attrs(&item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.visibility(&item.vis);
         self.word("enum ");
         self.ident(&item.ident);
@@ -70,7 +124,8 @@ impl Printer {
             self.word(",");
             self.hardbreak();
         }
-        self.offset(-INDENT);
+        self.trailing_comments(item.brace_token.span);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
         self.hardbreak();
@@ -91,7 +146,7 @@ impl Printer {
 
     fn item_fn(&mut self, item: &ItemFn) {
         self.outer_attrs(&item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.visibility(&item.vis);
         self.signature(
             &item.sig,
@@ -102,10 +157,8 @@ impl Printer {
         self.word("{");
         self.hardbreak_if_nonempty();
         self.inner_attrs(&item.attrs);
-        for stmt in item.block.stmts.iter().delimited() {
-            self.stmt(&stmt, stmt.is_last);
-        }
-        self.offset(-INDENT);
+        self.stmts(&item.block);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
         self.hardbreak();
@@ -113,7 +166,7 @@ impl Printer {
 
     fn item_foreign_mod(&mut self, item: &ItemForeignMod) {
         self.outer_attrs(&item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         if item.unsafety.is_some() {
             self.word("unsafe ");
         }
@@ -124,7 +177,8 @@ impl Printer {
         for foreign_item in &item.items {
             self.foreign_item(foreign_item);
         }
-        self.offset(-INDENT);
+        self.trailing_comments(item.brace_token.span);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
         self.hardbreak();
@@ -132,9 +186,9 @@ impl Printer {
 
     fn item_impl(&mut self, item: &ItemImpl) {
         self.outer_attrs(&item.attrs);
-        self.cbox(INDENT);
-        self.ibox(-INDENT);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
+        self.ibox(-self.indent_unit);
+        self.cbox(self.indent_unit);
         if item.defaultness.is_some() {
             self.word("default ");
         }
@@ -162,7 +216,8 @@ impl Printer {
         for impl_item in &item.items {
             self.impl_item(impl_item);
         }
-        self.offset(-INDENT);
+        self.trailing_comments(item.brace_token.span);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
         self.hardbreak();
@@ -171,27 +226,31 @@ impl Printer {
     fn item_macro(&mut self, item: &ItemMacro) {
         self.outer_attrs(&item.attrs);
         let semicolon = mac::requires_semi(&item.mac.delimiter);
-        self.mac(&item.mac, item.ident.as_ref(), semicolon);
+        self.mac(
+            &item.mac,
+            item.ident.as_ref(),
+            semicolon,
+            MacroPosition::Item,
+        );
         self.hardbreak();
     }
 
     fn item_mod(&mut self, item: &ItemMod) {
         self.outer_attrs(&item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.visibility(&item.vis);
         if item.unsafety.is_some() {
             self.word("unsafe ");
         }
         self.word("mod ");
         self.ident(&item.ident);
-        if let Some((_brace, items)) = &item.content {
+        if let Some((brace, items)) = &item.content {
             self.word(" {");
             self.hardbreak_if_nonempty();
             self.inner_attrs(&item.attrs);
-            for item in items {
-                self.item(item);
-            }
-            self.offset(-INDENT);
+            self.items(items);
+            self.trailing_comments(brace.span);
+            self.offset(-self.indent_unit);
             self.end();
             self.word("}");
         } else {
@@ -220,7 +279,7 @@ impl Printer {
 
     fn item_struct(&mut self, item: &ItemStruct) {
         self.outer_attrs(&item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.visibility(&item.vis);
         self.word("struct ");
         self.ident(&item.ident);
@@ -235,7 +294,8 @@ impl Printer {
                     self.word(",");
                     self.hardbreak();
                 }
-                self.offset(-INDENT);
+                self.trailing_comments(fields.brace_token.span);
+                self.offset(-self.indent_unit);
                 self.end();
                 self.word("}");
             }
@@ -254,7 +314,7 @@ impl Printer {
 
     fn item_trait(&mut self, item: &ItemTrait) {
         self.outer_attrs(&item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.visibility(&item.vis);
         if item.unsafety.is_some() {
             self.word("unsafe ");
@@ -280,7 +340,8 @@ impl Printer {
         for trait_item in &item.items {
             self.trait_item(trait_item);
         }
-        self.offset(-INDENT);
+        self.trailing_comments(item.brace_token.span);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
         self.hardbreak();
@@ -288,7 +349,7 @@ impl Printer {
 
     fn item_trait_alias(&mut self, item: &ItemTraitAlias) {
         self.outer_attrs(&item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.visibility(&item.vis);
         self.word("trait ");
         self.ident(&item.ident);
@@ -309,7 +370,7 @@ impl Printer {
 
     fn item_type(&mut self, item: &ItemType) {
         self.outer_attrs(&item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.visibility(&item.vis);
         self.word("type ");
         self.ident(&item.ident);
@@ -317,7 +378,7 @@ impl Printer {
         self.where_clause_oneline(&item.generics.where_clause);
         self.word("= ");
         self.neverbreak();
-        self.ibox(-INDENT);
+        self.ibox(-self.indent_unit);
         self.ty(&item.ty);
         self.end();
         self.word(";");
@@ -327,7 +388,7 @@ impl Printer {
 
     fn item_union(&mut self, item: &ItemUnion) {
         self.outer_attrs(&item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.visibility(&item.vis);
         self.word("union ");
         self.ident(&item.ident);
@@ -340,20 +401,25 @@ impl Printer {
             self.word(",");
             self.hardbreak();
         }
-        self.offset(-INDENT);
+        self.trailing_comments(item.fields.brace_token.span);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
         self.hardbreak();
     }
 
     fn item_use(&mut self, item: &ItemUse) {
+        self.item_use_with_tree(item, &item.tree);
+    }
+
+    pub fn item_use_with_tree(&mut self, item: &ItemUse, tree: &UseTree) {
         self.outer_attrs(&item.attrs);
         self.visibility(&item.vis);
         self.word("use ");
         if item.leading_colon.is_some() {
             self.word("::");
         }
-        self.use_tree(&item.tree);
+        self.use_tree(tree);
         self.word(";");
         self.hardbreak();
     }
@@ -361,7 +427,7 @@ impl Printer {
     #[cfg(not(feature = "verbatim"))]
     fn item_verbatim(&mut self, item: &TokenStream) {
         if !item.is_empty() {
-            unimplemented!("Item::Verbatim `{}`", item);
+            self.unsupported("Item::Verbatim", item);
         }
         self.hardbreak();
     }
@@ -414,6 +480,7 @@ impl Printer {
             vis: Visibility,
             ident: Ident,
             args: Option<TokenStream>,
+            brace_token: token::Brace,
             body: TokenStream,
         }
 
@@ -538,13 +605,14 @@ impl Printer {
                         None
                     };
                     let brace_content;
-                    braced!(brace_content in input);
+                    let brace_token = braced!(brace_content in input);
                     let body: TokenStream = brace_content.parse()?;
                     Ok(ItemVerbatim::Macro2(Macro2 {
                         attrs,
                         vis,
                         ident,
                         args,
+                        brace_token,
                         body,
                     }))
                 } else if lookahead.peek(Token![static]) {
@@ -573,9 +641,10 @@ impl Printer {
             }
         }
 
-        let item: ItemVerbatim = match syn::parse2(tokens.clone()) {
-            Ok(item) => item,
-            Err(_) => unimplemented!("Item::Verbatim `{}`", tokens),
+        let Ok(item) = syn::parse2::<ItemVerbatim>(tokens.clone()) else {
+            self.unsupported("Item::Verbatim", tokens);
+            self.hardbreak();
+            return;
         };
 
         match item {
@@ -594,9 +663,9 @@ impl Printer {
             }
             ItemVerbatim::ImplFlexible(item) => {
                 self.outer_attrs(&item.attrs);
-                self.cbox(INDENT);
-                self.ibox(-INDENT);
-                self.cbox(INDENT);
+                self.cbox(self.indent_unit);
+                self.ibox(-self.indent_unit);
+                self.cbox(self.indent_unit);
                 self.visibility(&item.vis);
                 if item.defaultness {
                     self.word("default ");
@@ -630,7 +699,7 @@ impl Printer {
                 for impl_item in &item.items {
                     self.impl_item(impl_item);
                 }
-                self.offset(-INDENT);
+                self.offset(-self.indent_unit);
                 self.end();
                 self.word("}");
                 self.hardbreak();
@@ -642,29 +711,49 @@ impl Printer {
                 self.ident(&item.ident);
                 if let Some(args) = &item.args {
                     self.word("(");
-                    self.cbox(INDENT);
+                    self.cbox(self.indent_unit);
                     self.zerobreak();
                     self.ibox(0);
                     self.macro_rules_tokens(args.clone(), true);
                     self.end();
                     self.zerobreak();
-                    self.offset(-INDENT);
+                    self.offset(-self.indent_unit);
                     self.end();
                     self.word(")");
+                    self.word(" {");
+                    if !item.body.is_empty() {
+                        self.neverbreak();
+                        self.cbox(self.indent_unit);
+                        self.hardbreak();
+                        if !self.macro_rules_transcriber(&item.body, item.brace_token.span) {
+                            self.ibox(0);
+                            self.macro_rules_tokens(item.body.clone(), false);
+                            self.end();
+                            self.hardbreak();
+                        }
+                        self.offset(-self.indent_unit);
+                        self.end();
+                    }
+                    self.word("}");
+                } else if mac::is_macro2_rules(&item.body) {
+                    self.word(" {");
+                    self.macro_rules_body(&item.body, ',', item.brace_token.span);
+                    self.word("}");
+                } else {
+                    self.word(" {");
+                    if !item.body.is_empty() {
+                        self.neverbreak();
+                        self.cbox(self.indent_unit);
+                        self.hardbreak();
+                        self.ibox(0);
+                        self.macro_rules_tokens(item.body.clone(), false);
+                        self.end();
+                        self.hardbreak();
+                        self.offset(-self.indent_unit);
+                        self.end();
+                    }
+                    self.word("}");
                 }
-                self.word(" {");
-                if !item.body.is_empty() {
-                    self.neverbreak();
-                    self.cbox(INDENT);
-                    self.hardbreak();
-                    self.ibox(0);
-                    self.macro_rules_tokens(item.body.clone(), false);
-                    self.end();
-                    self.hardbreak();
-                    self.offset(-INDENT);
-                    self.end();
-                }
-                self.word("}");
                 self.hardbreak();
             }
             ItemVerbatim::StaticFlexible(item) => {
@@ -681,7 +770,7 @@ impl Printer {
                     self.word("::");
                     self.use_tree(&item.trees[0].inner);
                 } else {
-                    self.cbox(INDENT);
+                    self.cbox(self.indent_unit);
                     self.word("{");
                     self.zerobreak();
                     self.ibox(0);
@@ -705,7 +794,7 @@ impl Printer {
                     }
                     self.end();
                     self.trailing_comma(true);
-                    self.offset(-INDENT);
+                    self.offset(-self.indent_unit);
                     self.word("}");
                     self.end();
                 }
@@ -758,7 +847,7 @@ impl Printer {
         {
             self.use_tree(&use_group.items[0]);
         } else {
-            self.cbox(INDENT);
+            self.cbox(self.indent_unit);
             self.word("{");
             self.zerobreak();
             self.ibox(0);
@@ -779,13 +868,14 @@ impl Printer {
             }
             self.end();
             self.trailing_comma(true);
-            self.offset(-INDENT);
+            self.offset(-self.indent_unit);
             self.word("}");
             self.end();
         }
     }
 
     fn foreign_item(&mut self, foreign_item: &ForeignItem) {
+        self.leading_comments(foreign_item);
         match foreign_item {
             #![cfg_attr(all(test, exhaustive), deny(non_exhaustive_omitted_patterns))]
             ForeignItem::Fn(item) => self.foreign_item_fn(item),
@@ -799,7 +889,7 @@ impl Printer {
 
     fn foreign_item_fn(&mut self, foreign_item: &ForeignItemFn) {
         self.outer_attrs(&foreign_item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.visibility(&foreign_item.vis);
         self.signature(
             &foreign_item.sig,
@@ -840,14 +930,14 @@ impl Printer {
     fn foreign_item_macro(&mut self, foreign_item: &ForeignItemMacro) {
         self.outer_attrs(&foreign_item.attrs);
         let semicolon = mac::requires_semi(&foreign_item.mac.delimiter);
-        self.mac(&foreign_item.mac, None, semicolon);
+        self.mac(&foreign_item.mac, None, semicolon, MacroPosition::Item);
         self.hardbreak();
     }
 
     #[cfg(not(feature = "verbatim"))]
     fn foreign_item_verbatim(&mut self, foreign_item: &TokenStream) {
         if !foreign_item.is_empty() {
-            unimplemented!("ForeignItem::Verbatim `{}`", foreign_item);
+            self.unsupported("ForeignItem::Verbatim", foreign_item);
         }
         self.hardbreak();
     }
@@ -916,9 +1006,10 @@ impl Printer {
             }
         }
 
-        let foreign_item: ForeignItemVerbatim = match syn::parse2(tokens.clone()) {
-            Ok(foreign_item) => foreign_item,
-            Err(_) => unimplemented!("ForeignItem::Verbatim `{}`", tokens),
+        let Ok(foreign_item) = syn::parse2::<ForeignItemVerbatim>(tokens.clone()) else {
+            self.unsupported("ForeignItem::Verbatim", tokens);
+            self.hardbreak();
+            return;
         };
 
         match foreign_item {
@@ -942,6 +1033,7 @@ impl Printer {
     }
 
     fn trait_item(&mut self, trait_item: &TraitItem) {
+        self.leading_comments(trait_item);
         match trait_item {
             #![cfg_attr(all(test, exhaustive), deny(non_exhaustive_omitted_patterns))]
             TraitItem::Const(item) => self.trait_item_const(item),
@@ -964,7 +1056,7 @@ impl Printer {
         if let Some((_eq_token, default)) = &trait_item.default {
             self.word(" = ");
             self.neverbreak();
-            self.expr(default, FixupContext::NONE);
+            self.const_value(default);
         }
         self.word(";");
         self.end();
@@ -973,7 +1065,7 @@ impl Printer {
 
     fn trait_item_fn(&mut self, trait_item: &TraitItemFn) {
         self.outer_attrs(&trait_item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.signature(
             &trait_item.sig,
             #[cfg(feature = "verbatim")]
@@ -984,10 +1076,8 @@ impl Printer {
             self.word("{");
             self.hardbreak_if_nonempty();
             self.inner_attrs(&trait_item.attrs);
-            for stmt in block.stmts.iter().delimited() {
-                self.stmt(&stmt, stmt.is_last);
-            }
-            self.offset(-INDENT);
+            self.stmts(block);
+            self.offset(-self.indent_unit);
             self.end();
             self.word("}");
         } else {
@@ -999,7 +1089,7 @@ impl Printer {
 
     fn trait_item_type(&mut self, trait_item: &TraitItemType) {
         self.outer_attrs(&trait_item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.word("type ");
         self.ident(&trait_item.ident);
         self.generics(&trait_item.generics);
@@ -1015,7 +1105,7 @@ impl Printer {
         if let Some((_eq_token, default)) = &trait_item.default {
             self.word(" = ");
             self.neverbreak();
-            self.ibox(-INDENT);
+            self.ibox(-self.indent_unit);
             self.ty(default);
             self.end();
         }
@@ -1027,14 +1117,14 @@ impl Printer {
     fn trait_item_macro(&mut self, trait_item: &TraitItemMacro) {
         self.outer_attrs(&trait_item.attrs);
         let semicolon = mac::requires_semi(&trait_item.mac.delimiter);
-        self.mac(&trait_item.mac, None, semicolon);
+        self.mac(&trait_item.mac, None, semicolon, MacroPosition::Item);
         self.hardbreak();
     }
 
     #[cfg(not(feature = "verbatim"))]
     fn trait_item_verbatim(&mut self, trait_item: &TokenStream) {
         if !trait_item.is_empty() {
-            unimplemented!("TraitItem::Verbatim `{}`", trait_item);
+            self.unsupported("TraitItem::Verbatim", trait_item);
         }
         self.hardbreak();
     }
@@ -1105,9 +1195,10 @@ impl Printer {
             }
         }
 
-        let impl_item: TraitItemVerbatim = match syn::parse2(tokens.clone()) {
-            Ok(impl_item) => impl_item,
-            Err(_) => unimplemented!("TraitItem::Verbatim `{}`", tokens),
+        let Ok(impl_item) = syn::parse2::<TraitItemVerbatim>(tokens.clone()) else {
+            self.unsupported("TraitItem::Verbatim", tokens);
+            self.hardbreak();
+            return;
         };
 
         match impl_item {
@@ -1136,6 +1227,7 @@ impl Printer {
     }
 
     fn impl_item(&mut self, impl_item: &ImplItem) {
+        self.leading_comments(impl_item);
         match impl_item {
             #![cfg_attr(all(test, exhaustive), deny(non_exhaustive_omitted_patterns))]
             ImplItem::Const(item) => self.impl_item_const(item),
@@ -1161,7 +1253,7 @@ impl Printer {
         self.ty(&impl_item.ty);
         self.word(" = ");
         self.neverbreak();
-        self.expr(&impl_item.expr, FixupContext::NONE);
+        self.const_value(&impl_item.expr);
         self.word(";");
         self.end();
         self.hardbreak();
@@ -1169,7 +1261,7 @@ impl Printer {
 
     fn impl_item_fn(&mut self, impl_item: &ImplItemFn) {
         self.outer_attrs(&impl_item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.visibility(&impl_item.vis);
         if impl_item.defaultness.is_some() {
             self.word("default ");
@@ -1183,10 +1275,8 @@ impl Printer {
         self.word("{");
         self.hardbreak_if_nonempty();
         self.inner_attrs(&impl_item.attrs);
-        for stmt in impl_item.block.stmts.iter().delimited() {
-            self.stmt(&stmt, stmt.is_last);
-        }
-        self.offset(-INDENT);
+        self.stmts(&impl_item.block);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
         self.hardbreak();
@@ -1194,7 +1284,7 @@ impl Printer {
 
     fn impl_item_type(&mut self, impl_item: &ImplItemType) {
         self.outer_attrs(&impl_item.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.visibility(&impl_item.vis);
         if impl_item.defaultness.is_some() {
             self.word("default ");
@@ -1204,7 +1294,7 @@ impl Printer {
         self.generics(&impl_item.generics);
         self.word(" = ");
         self.neverbreak();
-        self.ibox(-INDENT);
+        self.ibox(-self.indent_unit);
         self.ty(&impl_item.ty);
         self.end();
         self.where_clause_oneline_semi(&impl_item.generics.where_clause);
@@ -1215,14 +1305,14 @@ impl Printer {
     fn impl_item_macro(&mut self, impl_item: &ImplItemMacro) {
         self.outer_attrs(&impl_item.attrs);
         let semicolon = mac::requires_semi(&impl_item.mac.delimiter);
-        self.mac(&impl_item.mac, None, semicolon);
+        self.mac(&impl_item.mac, None, semicolon, MacroPosition::Item);
         self.hardbreak();
     }
 
     #[cfg(not(feature = "verbatim"))]
     fn impl_item_verbatim(&mut self, impl_item: &TokenStream) {
         if !impl_item.is_empty() {
-            unimplemented!("ImplItem::Verbatim `{}`", impl_item);
+            self.unsupported("ImplItem::Verbatim", impl_item);
         }
         self.hardbreak();
     }
@@ -1281,9 +1371,10 @@ impl Printer {
             }
         }
 
-        let impl_item: ImplItemVerbatim = match syn::parse2(tokens.clone()) {
-            Ok(impl_item) => impl_item,
-            Err(_) => unimplemented!("ImplItem::Verbatim `{}`", tokens),
+        let Ok(impl_item) = syn::parse2::<ImplItemVerbatim>(tokens.clone()) else {
+            self.unsupported("ImplItem::Verbatim", tokens);
+            self.hardbreak();
+            return;
         };
 
         match impl_item {
@@ -1344,6 +1435,7 @@ impl Printer {
         self.cbox(0);
         self.zerobreak();
         for input in signature.inputs.iter().delimited() {
+            self.leading_comments(*input);
             self.fn_arg(&input);
             let is_last = input.is_last && signature.variadic.is_none();
             self.trailing_comma(is_last);
@@ -1352,10 +1444,11 @@ impl Printer {
             self.variadic(variadic);
             self.zerobreak();
         }
-        self.offset(-INDENT);
+        self.trailing_comments(signature.paren_token.span);
+        self.offset(-self.indent_unit);
         self.end();
         self.word(")");
-        self.cbox(-INDENT);
+        self.cbox(-self.indent_unit);
         self.return_type(&signature.output);
         self.end();
     }
@@ -1426,7 +1519,6 @@ mod verbatim {
     use crate::algorithm::Printer;
     use crate::fixup::FixupContext;
     use crate::iter::IterDelimited;
-    use crate::INDENT;
     use syn::ext::IdentExt;
     use syn::parse::{Parse, ParseStream, Result};
     use syn::{
@@ -1692,10 +1784,10 @@ mod verbatim {
         }
     }
 
-    impl Printer {
+    impl Printer<'_> {
         pub fn flexible_item_const(&mut self, item: &FlexibleItemConst) {
             self.outer_attrs(&item.attrs);
-            self.cbox(INDENT);
+            self.cbox(self.indent_unit);
             self.visibility(&item.vis);
             if item.defaultness {
                 self.word("default ");
@@ -1704,13 +1796,13 @@ mod verbatim {
             self.ident(&item.ident);
             self.generics(&item.generics);
             self.word(": ");
-            self.cbox(-INDENT);
+            self.cbox(-self.indent_unit);
             self.ty(&item.ty);
             self.end();
             if let Some(value) = &item.value {
                 self.word(" = ");
                 self.neverbreak();
-                self.ibox(-INDENT);
+                self.ibox(-self.indent_unit);
                 self.expr(value, FixupContext::NONE);
                 self.end();
             }
@@ -1721,7 +1813,7 @@ mod verbatim {
 
         pub fn flexible_item_fn(&mut self, item: &FlexibleItemFn) {
             self.outer_attrs(&item.attrs);
-            self.cbox(INDENT);
+            self.cbox(self.indent_unit);
             self.visibility(&item.vis);
             if item.defaultness {
                 self.word("default ");
@@ -1735,7 +1827,7 @@ mod verbatim {
                 for stmt in body.iter().delimited() {
                     self.stmt(&stmt, stmt.is_last);
                 }
-                self.offset(-INDENT);
+                self.offset(-self.indent_unit);
                 self.end();
                 self.word("}");
             } else {
@@ -1769,7 +1861,7 @@ mod verbatim {
 
         pub fn flexible_item_type(&mut self, item: &FlexibleItemType) {
             self.outer_attrs(&item.attrs);
-            self.cbox(INDENT);
+            self.cbox(self.indent_unit);
             self.visibility(&item.vis);
             if item.defaultness {
                 self.word("default ");
@@ -1790,7 +1882,7 @@ mod verbatim {
                 self.where_clause_oneline(&item.generics.where_clause);
                 self.word("= ");
                 self.neverbreak();
-                self.ibox(-INDENT);
+                self.ibox(-self.indent_unit);
                 self.ty(definition);
                 self.end();
                 self.where_clause_oneline_semi(&item.where_clause_after_eq);
@@ -0,0 +1,216 @@
+//! The line-breaking engine behind [`unparse`][crate::unparse], for laying
+//! out text other than Rust syntax trees.
+//!
+//! A document is built as a sequence of words and breaks grouped into nested
+//! boxes. When a box does not fit in the remaining width of the line, its
+//! breaks turn into newlines: in a *consistent* box every break of the box
+//! breaks, while in an *inconsistent* box only those breaks that are needed to
+//! keep the content within the margin do. Breaks that do not break print as
+//! blank space.
+//!
+//! ```
+//! use prettyplease::layout::Doc;
+//!
+//! fn call(doc: &mut Doc, name: &'static str, args: &[&'static str]) {
+//!     doc.cbox(4);
+//!     doc.word(name);
+//!     doc.word("(");
+//!     doc.zerobreak();
+//!     for (i, arg) in args.iter().enumerate() {
+//!         doc.word(*arg);
+//!         doc.trailing_comma(i + 1 == args.len());
+//!     }
+//!     doc.offset(-4);
+//!     doc.end();
+//!     doc.word(");");
+//! }
+//!
+//! let mut doc = Doc::new(24, 0);
+//! call(&mut doc, "f", &["a", "b"]);
+//! doc.hardbreak();
+//! call(&mut doc, "function", &["argument", "another"]);
+//! doc.hardbreak();
+//!
+//! assert_eq!(
+//!     doc.finish(),
+//!     "f(a, b);\nfunction(\n    argument,\n    another,\n);\n",
+//! );
+//! ```
+//!
+//! The width of text is measured in bytes, so words are expected to be ASCII
+//! for the margin to be respected exactly.
+
+use crate::algorithm::Printer;
+use crate::config::{Config, IndentStyle};
+use std::borrow::Cow;
+use std::fmt::{self, Debug};
+
+/// A document under construction.
+///
+/// Every [`ibox`][Doc::ibox] and [`cbox`][Doc::cbox] must be closed by a
+/// matching [`end`][Doc::end] before the document is finished, and no line may
+/// be indented to the left of the start of the document. Methods that would
+/// break either rule panic rather than producing garbled output.
+pub struct Doc {
+    printer: Printer<'static>,
+    // Indentation, relative to the start of the document, of the lines broken
+    // inside each open box.
+    indents: Vec<isize>,
+}
+
+impl Doc {
+    /// Starts an empty document laid out to fit within `max_width` columns,
+    /// indented with spaces. Every line is allowed at least `min_space`
+    /// columns after its indentation, even if that takes it past the margin.
+    pub fn new(max_width: usize, min_space: usize) -> Self {
+        Doc::with_config(&Config {
+            max_width,
+            min_space,
+            ..Config::default()
+        })
+    }
+
+    /// Like [`new`][Doc::new], but indents with one tab for every
+    /// `tab_width` columns of indentation, and spaces for the remainder. For
+    /// the purpose of line width, each tab counts as `tab_width` columns.
+    pub fn with_tabs(max_width: usize, min_space: usize, tab_width: usize) -> Self {
+        Doc::with_config(&Config {
+            max_width,
+            min_space,
+            indent_width: tab_width,
+            indent_style: IndentStyle::Tabs,
+            ..Config::default()
+        })
+    }
+
+    fn with_config(config: &Config) -> Self {
+        Doc {
+            printer: Printer::new(config),
+            indents: Vec::new(),
+        }
+    }
+
+    /// Opens an inconsistent box: only the breaks needed to fit the margin are
+    /// taken. Lines broken inside the box are indented by `indent` columns
+    /// relative to the indentation at which the box began.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `indent` would put lines of the box left of the start of the
+    /// document.
+    pub fn ibox(&mut self, indent: isize) {
+        self.open(indent);
+        self.printer.ibox(indent);
+    }
+
+    /// Opens a consistent box: if the box does not fit on the current line,
+    /// every one of its breaks is taken. Lines broken inside the box are
+    /// indented by `indent` columns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `indent` would put lines of the box left of the start of the
+    /// document.
+    pub fn cbox(&mut self, indent: isize) {
+        self.open(indent);
+        self.printer.cbox(indent);
+    }
+
+    /// Closes the most recently opened box.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no open box.
+    pub fn end(&mut self) {
+        assert!(
+            self.indents.pop().is_some(),
+            "Doc::end called without an open box",
+        );
+        self.printer.end();
+    }
+
+    /// Appends text that is never broken.
+    pub fn word<S: Into<Cow<'static, str>>>(&mut self, word: S) {
+        self.printer.word(word);
+    }
+
+    /// Appends a break that prints as a space if not taken.
+    pub fn space(&mut self) {
+        self.printer.space();
+    }
+
+    /// Appends a break that prints as nothing if not taken.
+    pub fn zerobreak(&mut self) {
+        self.printer.zerobreak();
+    }
+
+    /// Appends a space that is never broken.
+    pub fn nbsp(&mut self) {
+        self.printer.nbsp();
+    }
+
+    /// Appends a break that is always taken, forcing every enclosing box to
+    /// break.
+    pub fn hardbreak(&mut self) {
+        self.printer.hardbreak();
+    }
+
+    /// Appends a break that prints nothing if not taken, and prevents the
+    /// enclosing box from breaking because of content that follows it on the
+    /// same line.
+    pub fn neverbreak(&mut self) {
+        self.printer.neverbreak();
+    }
+
+    /// Appends the separator after an element of a comma-separated list. After
+    /// the last element, this is a break that prints a trailing comma only if
+    /// taken. Otherwise it is a comma followed by a space break.
+    pub fn trailing_comma(&mut self, is_last: bool) {
+        self.printer.trailing_comma(is_last);
+    }
+
+    /// Adjusts the indentation of the most recently appended break by
+    /// `offset` columns, if that break is taken. Typically used with a
+    /// negative offset before closing a box, so that the closing delimiter
+    /// lines up with the line on which the box was opened.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the most recently appended item is a word or the end of a
+    /// box, or if the offset would indent the following line left of the
+    /// start of the document.
+    pub fn offset(&mut self, offset: isize) {
+        let indent = self.indents.last().copied().unwrap_or(0) + offset;
+        assert!(indent >= 0, "Doc::offset would indent by {indent} columns");
+        self.printer.offset(offset);
+    }
+
+    /// Lays out the document and returns the resulting text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a box is still open.
+    pub fn finish(self) -> String {
+        assert!(
+            self.indents.is_empty(),
+            "Doc::finish called with {} unclosed box(es)",
+            self.indents.len(),
+        );
+        match self.printer.eof() {
+            Ok(string) => string,
+            Err(error) => error.panic(),
+        }
+    }
+
+    fn open(&mut self, indent: isize) {
+        let indent = self.indents.last().copied().unwrap_or(0) + indent;
+        assert!(indent >= 0, "Doc box would indent by {indent} columns");
+        self.indents.push(indent);
+    }
+}
+
+impl Debug for Doc {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("Doc").finish_non_exhaustive()
+    }
+}
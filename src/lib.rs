@@ -347,14 +347,19 @@
 mod algorithm;
 mod attr;
 mod classify;
+mod comment;
+mod config;
 mod convenience;
 mod data;
+mod error;
 mod expr;
 mod file;
 mod fixup;
 mod generics;
+mod imports;
 mod item;
 mod iter;
+pub mod layout;
 mod lifetime;
 mod lit;
 mod mac;
@@ -362,24 +367,484 @@ mod pat;
 mod path;
 mod precedence;
 mod ring;
+mod source_map;
+#[cfg(feature = "span-locations")]
+mod span;
 mod stmt;
 mod token;
 mod ty;
+#[cfg(feature = "verify")]
+mod verify;
 
 use crate::algorithm::Printer;
-use syn::File;
+use crate::fixup::FixupContext;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use syn::{Expr, File, Item, Pat, Stmt, Type};
 
-// Target line width.
-const MARGIN: isize = 89;
+pub use crate::config::{
+    Config, ImportsGranularity, IndentStyle, LiteralCase, LiteralEscapes, LiteralSuffix, MacroArgs,
+    MacroRegistry,
+};
+pub use crate::error::Error;
+#[cfg(feature = "span-locations")]
+pub use crate::source_map::{Mapping, NodeKind, SourceMap};
+#[cfg(feature = "verify")]
+pub use crate::verify::{VerifyError, VerifyErrorKind};
 
-// Number of spaces increment at each level of block indentation.
-const INDENT: isize = 4;
+pub fn unparse(file: &File) -> String {
+    unparse_with(file, &Config::default())
+}
 
-// Every line is allowed at least this much space, even if highly indented.
-const MIN_SPACE: isize = 60;
+/// Like [`unparse`], but with the layout controlled by a [`Config`].
+///
+/// ```
+/// # let syntax_tree: syn::File = syn::parse_quote!(fn main() {});
+/// let mut config = prettyplease::Config::default();
+/// config.max_width = 100;
+/// let formatted = prettyplease::unparse_with(&syntax_tree, &config);
+/// ```
+pub fn unparse_with(file: &File, config: &Config) -> String {
+    match try_unparse_with(file, config) {
+        Ok(string) => string,
+        Err(error) => error.panic(),
+    }
+}
 
-pub fn unparse(file: &File) -> String {
-    let mut p = Printer::new();
+/// Like [`unparse`], but returns an error instead of panicking if the syntax
+/// tree contains a node that cannot be printed, such as a `Verbatim` node
+/// containing unrecognized syntax.
+///
+/// # Errors
+///
+/// Returns an [`Error`] describing the first node that cannot be printed.
+///
+/// ```
+/// let item = syn::Item::Verbatim(quote::quote!(not an item));
+/// let syntax_tree = syn::File {
+///     shebang: None,
+///     attrs: Vec::new(),
+///     items: vec![item],
+/// };
+/// let error = prettyplease::try_unparse(&syntax_tree).unwrap_err();
+/// assert_eq!(error.kind(), "Item::Verbatim");
+/// assert_eq!(error.tokens(), "not an item");
+/// ```
+pub fn try_unparse(file: &File) -> Result<String, Error> {
+    try_unparse_with(file, &Config::default())
+}
+
+/// Like [`unparse_with`], but returns an error instead of panicking if the
+/// syntax tree contains a node that cannot be printed.
+///
+/// # Errors
+///
+/// Returns an [`Error`] describing the first node that cannot be printed.
+pub fn try_unparse_with(file: &File, config: &Config) -> Result<String, Error> {
+    let mut p = Printer::new(config);
     p.file(file);
     p.eof()
 }
+
+/// Like [`unparse_with`], but also reproduces the `//` and `/* */` comments
+/// from the original source text, which are not part of the syntax tree.
+///
+/// The `file` must have been parsed from `source` with `syn::parse_file` while
+/// the `span-locations` feature of this crate is enabled, so that its spans
+/// refer to byte positions in `source`. Each comment is printed on its own
+/// line in front of the nearest following item, statement, field, variant or
+/// match arm, or before the closing brace of the enclosing block.
+///
+/// ```
+/// let source = "fn main() {\n    // says hello\n    println!(\"hello\");\n}\n";
+/// let syntax_tree = syn::parse_file(source).unwrap();
+/// let config = prettyplease::Config::default();
+/// let formatted = prettyplease::unparse_with_comments(&syntax_tree, source, &config);
+/// assert_eq!(formatted, source);
+/// ```
+#[cfg(feature = "span-locations")]
+pub fn unparse_with_comments(file: &File, source: &str, config: &Config) -> String {
+    match try_unparse_with_comments(file, source, config) {
+        Ok(string) => string,
+        Err(error) => error.panic(),
+    }
+}
+
+/// Like [`unparse_with_comments`], but returns an error instead of panicking
+/// if the syntax tree contains a node that cannot be printed.
+///
+/// # Errors
+///
+/// Returns an [`Error`] describing the first node that cannot be printed.
+#[cfg(feature = "span-locations")]
+pub fn try_unparse_with_comments(
+    file: &File,
+    source: &str,
+    config: &Config,
+) -> Result<String, Error> {
+    let mut p = Printer::new(config);
+    p.comments = comment::parse(source);
+    p.file(file);
+    p.eof()
+}
+
+/// Like [`unparse_with`], but also returns where in the output each item,
+/// statement and expression of the syntax tree was printed.
+///
+/// Each [`Mapping`] pairs a byte range of the output with the `Span` of the
+/// node that produced it. When the syntax tree was parsed from source text,
+/// those spans carry the original line and column, which is enough to point
+/// errors in the formatted code back to the source, or to emit `#line`-style
+/// annotations:
+///
+/// ```
+/// let source = "fn main() {\n    let x = 1;\n}\n";
+/// let syntax_tree = syn::parse_file(source).unwrap();
+/// let config = prettyplease::Config::default();
+/// let (formatted, source_map) = prettyplease::unparse_with_source_map(&syntax_tree, &config);
+///
+/// for mapping in source_map.mappings() {
+///     if mapping.kind() == prettyplease::NodeKind::Stmt {
+///         let output_line = mapping.output_line();
+///         let source_line = mapping.span().start().line;
+///         println!("output line {} is #line {}", output_line, source_line);
+///     }
+/// }
+///
+/// let offset = formatted.find('1').unwrap();
+/// let mapping = source_map.find(offset).unwrap();
+/// assert_eq!(mapping.kind(), prettyplease::NodeKind::Expr);
+/// assert_eq!(&formatted[mapping.output_range()], "1");
+/// ```
+#[cfg(feature = "span-locations")]
+pub fn unparse_with_source_map(file: &File, config: &Config) -> (String, SourceMap) {
+    let mut p = Printer::new(config);
+    p.source_map = Some(source_map::Recorder::default());
+    p.file(file);
+    match p.eof_with_source_map() {
+        Ok(output) => output,
+        Err(error) => error.panic(),
+    }
+}
+
+/// Like [`unparse`], but writes the output to an [`io::Write`] incrementally
+/// instead of building the whole formatted file in memory.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// # let syntax_tree: syn::File = syn::parse_quote!(fn main() {});
+/// let file = std::fs::File::create("generated.rs")?;
+/// prettyplease::unparse_to_writer(&syntax_tree, std::io::BufWriter::new(file))?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns the first error from writing to `writer`, or an error of kind
+/// [`io::ErrorKind::InvalidData`] wrapping an [`Error`] if the syntax tree
+/// contains a node that cannot be printed.
+///
+/// Output is written as it is laid out, so on error `writer` may hold the
+/// beginning of the formatted file. No output from the unprintable node or
+/// anything after it is written.
+pub fn unparse_to_writer(file: &File, writer: impl io::Write) -> io::Result<()> {
+    unparse_to_writer_with(file, &Config::default(), writer)
+}
+
+/// Like [`unparse_to_writer`], but with the layout controlled by a [`Config`].
+///
+/// # Errors
+///
+/// Fails under the same conditions as [`unparse_to_writer`].
+pub fn unparse_to_writer_with(
+    file: &File,
+    config: &Config,
+    writer: impl io::Write,
+) -> io::Result<()> {
+    struct Adapter<W> {
+        inner: W,
+        error: io::Result<()>,
+    }
+
+    impl<W: io::Write> fmt::Write for Adapter<W> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.inner.write_all(s.as_bytes()).map_err(|error| {
+                self.error = Err(error);
+                fmt::Error
+            })
+        }
+    }
+
+    let mut adapter = Adapter {
+        inner: writer,
+        error: Ok(()),
+    };
+    match unparse_to_fmt_writer_with(file, config, &mut adapter) {
+        Ok(Ok(())) => adapter.inner.flush(),
+        Ok(Err(fmt::Error)) => adapter.error,
+        Err(error) => Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+    }
+}
+
+/// Like [`unparse`], but writes the output to a [`fmt::Write`] incrementally
+/// instead of building the whole formatted file in memory.
+///
+/// # Errors
+///
+/// Returns the first error from writing to `writer`. Whatever was written
+/// before the failure is left in place.
+///
+/// # Panics
+///
+/// Panics if the syntax tree contains a node that cannot be printed, after
+/// part of the output may already have been written. Use
+/// [`unparse_to_fmt_writer_with`] to get an error instead.
+pub fn unparse_to_fmt_writer(file: &File, writer: impl fmt::Write) -> fmt::Result {
+    match unparse_to_fmt_writer_with(file, &Config::default(), writer) {
+        Ok(result) => result,
+        Err(error) => error.panic(),
+    }
+}
+
+/// Like [`unparse_to_fmt_writer`], but with the layout controlled by a
+/// [`Config`], and returning an error instead of panicking if the syntax tree
+/// contains a node that cannot be printed.
+///
+/// # Errors
+///
+/// The outer `Result` is an [`Error`] if the syntax tree contains a node that
+/// cannot be printed. The inner one is the first error from writing to
+/// `writer`.
+///
+/// Output is written as it is laid out, so on error `writer` may hold the
+/// beginning of the formatted file. No output from the unprintable node or
+/// anything after it is written.
+///
+/// ```
+/// let item = syn::Item::Verbatim(quote::quote!(not an item));
+/// let syntax_tree = syn::File {
+///     shebang: None,
+///     attrs: Vec::new(),
+///     items: vec![item],
+/// };
+/// let config = prettyplease::Config::default();
+/// let mut string = String::new();
+/// let result = prettyplease::unparse_to_fmt_writer_with(&syntax_tree, &config, &mut string);
+/// assert_eq!(result.unwrap_err().kind(), "Item::Verbatim");
+/// assert_eq!(string, "");
+/// ```
+pub fn unparse_to_fmt_writer_with(
+    file: &File,
+    config: &Config,
+    mut writer: impl fmt::Write,
+) -> Result<fmt::Result, Error> {
+    let mut p = Printer::with_sink(config, &mut writer);
+    p.file(file);
+    p.eof_to_sink()
+}
+
+/// Formats `file` and writes it to `path`, unless the file at `path` already
+/// has exactly that content.
+///
+/// Intended for build scripts: leaving an unchanged file untouched preserves
+/// its modification time, so that Cargo does not consider the generated code
+/// changed and rebuild whatever depends on it. The new content is written to a
+/// temporary file in the same directory and then renamed over `path`, so a
+/// concurrent reader never observes a partially written file.
+///
+/// Returns whether the file was written.
+///
+/// # Errors
+///
+/// Returns an error if reading the existing file, writing the temporary file
+/// or renaming it over `path` fails.
+///
+/// # Panics
+///
+/// Panics if the syntax tree contains a node that cannot be printed.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// # let syntax_tree: syn::File = syn::parse_quote!(fn main() {});
+/// let out_dir = std::env::var_os("OUT_DIR").unwrap();
+/// let path = std::path::Path::new(&out_dir).join("generated.rs");
+/// prettyplease::write_if_changed(path, &syntax_tree)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn write_if_changed(path: impl AsRef<Path>, file: &File) -> io::Result<bool> {
+    write_if_changed_with(path, file, &Config::default())
+}
+
+/// Like [`write_if_changed`], but with the output controlled by a [`Config`],
+/// for example to begin the file with a [`header`][Config::header].
+///
+/// # Errors
+///
+/// Fails under the same conditions as [`write_if_changed`].
+///
+/// # Panics
+///
+/// Panics under the same conditions as [`write_if_changed`].
+pub fn write_if_changed_with(
+    path: impl AsRef<Path>,
+    file: &File,
+    config: &Config,
+) -> io::Result<bool> {
+    let path = path.as_ref();
+    let formatted = unparse_with(file, config);
+    match fs::read(path) {
+        Ok(existing) if existing == formatted.as_bytes() => return Ok(false),
+        Ok(_) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => return Err(error),
+    }
+
+    // Unique among concurrent calls, whether from other processes or from
+    // other threads of this one.
+    static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let tmp_id = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut tmp_name = path.file_name().unwrap_or_default().to_owned();
+    tmp_name.push(format!(".{}.{}.tmp", process::id(), tmp_id));
+    let tmp_path = path.with_file_name(tmp_name);
+    fs::write(&tmp_path, formatted)?;
+    if let Err(error) = fs::rename(&tmp_path, path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(error);
+    }
+    Ok(true)
+}
+
+/// Formats `file` and checks that the output round trips, returning the
+/// formatted code if it does.
+///
+/// The output is parsed back with `syn` and must contain the same tokens as
+/// `file`, up to differences that do not affect meaning, such as parentheses
+/// added for precedence or trailing commas. Such differences are accepted only
+/// if the reparsed output has the same structure as `file`, so dropping the
+/// parentheses or comma of a tuple is an error. Formatting the reparsed output
+/// must then reproduce it exactly. This catches printer bugs that would
+/// otherwise surface as generated code failing to compile, or as a diff every
+/// time the generator reruns on its own output.
+///
+/// Passing verification does not guarantee that the output compiles. The
+/// checks only go as far as `syn` parsing, which accepts some code that rustc
+/// rejects, for example `use a::self;` or a `break` outside of a loop.
+///
+/// # Errors
+///
+/// Returns a [`VerifyError`] describing the first check that failed.
+/// Idempotence failures render as a unified diff between the two formatting
+/// passes.
+///
+/// ```
+/// # let syntax_tree: syn::File = syn::parse_quote!(fn main() {});
+/// let config = prettyplease::Config::default();
+/// match prettyplease::verify(&syntax_tree, &config) {
+///     Ok(formatted) => print!("{}", formatted),
+///     Err(error) => panic!("{}", error),
+/// }
+/// ```
+#[cfg(feature = "verify")]
+pub fn verify(file: &File, config: &Config) -> Result<String, VerifyError> {
+    verify::check(file, config, false)
+}
+
+/// Like [`verify`], but additionally checks that the reparsed output has the
+/// same structure as `file`, and reports the first node that was printed
+/// wrongly.
+///
+/// Both syntax trees are compared after removing `Expr::Paren` and
+/// `Expr::Group` nodes, so a parenthesized expression is considered the same
+/// as the expression without parentheses. What must agree is how expressions
+/// nest: a node whose parentheses were needed for precedence but were left
+/// out of the output would parse back as part of some other expression,
+/// changing the meaning of the code without any error. This check is slower
+/// than `verify` and intended for debugging the printer or a code generator.
+///
+/// As with `verify`, the output is only parsed by `syn`, so it may still be
+/// rejected by the compiler.
+///
+/// # Errors
+///
+/// Fails on the same checks as [`verify`]. On a structural mismatch, the
+/// [`VerifyError`] names the innermost item, statement or expression whose
+/// printed text parses back differently, along with its location in the output
+/// and both structures with grouping made explicit.
+#[cfg(feature = "verify")]
+pub fn verify_semantics(file: &File, config: &Config) -> Result<String, VerifyError> {
+    verify::check(file, config, true)
+}
+
+/// Format a single expression.
+///
+/// The expression is printed standalone, with parentheses inserted only where
+/// required by precedence within the expression itself. There is no trailing
+/// newline.
+pub fn unparse_expr(expr: &Expr) -> String {
+    unparse_expr_with(expr, &Config::default())
+}
+
+/// Like [`unparse_expr`], but with the output controlled by a [`Config`].
+pub fn unparse_expr_with(expr: &Expr, config: &Config) -> String {
+    unparse_fragment(config, |p| p.expr(expr, FixupContext::NONE))
+}
+
+/// Format a single item, such as a function or struct, followed by a newline.
+pub fn unparse_item(item: &Item) -> String {
+    unparse_item_with(item, &Config::default())
+}
+
+/// Like [`unparse_item`], but with the output controlled by a [`Config`].
+pub fn unparse_item_with(item: &Item, config: &Config) -> String {
+    unparse_fragment(config, |p| p.item(item))
+}
+
+/// Format a single type. There is no trailing newline.
+pub fn unparse_type(ty: &Type) -> String {
+    unparse_type_with(ty, &Config::default())
+}
+
+/// Like [`unparse_type`], but with the output controlled by a [`Config`].
+pub fn unparse_type_with(ty: &Type, config: &Config) -> String {
+    unparse_fragment(config, |p| p.ty(ty))
+}
+
+/// Format a single pattern. There is no trailing newline.
+pub fn unparse_pat(pat: &Pat) -> String {
+    unparse_pat_with(pat, &Config::default())
+}
+
+/// Like [`unparse_pat`], but with the output controlled by a [`Config`].
+pub fn unparse_pat_with(pat: &Pat, config: &Config) -> String {
+    unparse_fragment(config, |p| p.pat(pat))
+}
+
+/// Format a single statement, followed by a newline.
+///
+/// Expressions in statement position are parenthesized as needed for the
+/// output to parse back as the same statement, for example `(match x {}) - 1;`.
+pub fn unparse_stmt(stmt: &Stmt) -> String {
+    unparse_stmt_with(stmt, &Config::default())
+}
+
+/// Like [`unparse_stmt`], but with the output controlled by a [`Config`].
+pub fn unparse_stmt_with(stmt: &Stmt, config: &Config) -> String {
+    unparse_fragment(config, |p| p.stmt(stmt, true))
+}
+
+fn unparse_fragment(config: &Config, print: impl FnOnce(&mut Printer<'_>)) -> String {
+    let mut p = Printer::new(config);
+    p.cbox(0);
+    print(&mut p);
+    p.end();
+    match p.eof() {
+        Ok(string) => string,
+        Err(error) => error.panic(),
+    }
+}
@@ -1,8 +1,11 @@
-use crate::algorithm::Printer;
+use crate::algorithm::{BreakToken, Printer};
+use crate::config::{LiteralCase, LiteralEscapes, LiteralSuffix};
+use crate::iter::IterDelimited;
 use proc_macro2::Literal;
+use std::fmt::Write as _;
 use syn::{Lit, LitBool, LitByte, LitByteStr, LitCStr, LitChar, LitFloat, LitInt, LitStr};
 
-impl Printer {
+impl Printer<'_> {
     pub fn lit(&mut self, lit: &Lit) {
         match lit {
             #![cfg_attr(all(test, exhaustive), deny(non_exhaustive_omitted_patterns))]
@@ -20,31 +23,183 @@ impl Printer {
     }
 
     pub fn lit_str(&mut self, lit: &LitStr) {
-        self.word(lit.token().to_string());
+        let repr = match self.escapes(lit.suffix()) {
+            Some(unicode) => string_repr("", lit.value().chars().map(Ok), unicode),
+            None => lit.token().to_string(),
+        };
+        let pieces = if self.config.wrap_string_literals {
+            string_pieces(&repr)
+        } else {
+            None
+        };
+        let Some(pieces) = pieces else {
+            self.word(repr);
+            return;
+        };
+        self.ibox(self.indent_unit);
+        for piece in pieces.iter().delimited() {
+            if !piece.is_first {
+                self.scan_break(BreakToken {
+                    pre_break: Some('\\'),
+                    reserve_pre_break: true,
+                    ..BreakToken::default()
+                });
+            }
+            self.word(piece.clone());
+        }
+        self.end();
     }
 
     fn lit_byte_str(&mut self, lit: &LitByteStr) {
-        self.word(lit.token().to_string());
+        let repr = match self.escapes(lit.suffix()) {
+            Some(unicode) => string_repr("b", lit.value().into_iter().map(byte_unit), unicode),
+            None => lit.token().to_string(),
+        };
+        self.word(repr);
     }
 
     fn lit_c_str(&mut self, lit: &LitCStr) {
-        self.word(lit.token().to_string());
+        let repr = match self.escapes(lit.suffix()) {
+            Some(unicode) => {
+                let value = lit.value();
+                match value.to_str() {
+                    Ok(value) => string_repr("c", value.chars().map(Ok), unicode),
+                    Err(_) => {
+                        string_repr("c", value.to_bytes().iter().map(|b| byte_unit(*b)), unicode)
+                    }
+                }
+            }
+            None => lit.token().to_string(),
+        };
+        self.word(repr);
     }
 
     fn lit_byte(&mut self, lit: &LitByte) {
-        self.word(lit.token().to_string());
+        let repr = match self.escapes(lit.suffix()) {
+            Some(unicode) => {
+                let mut repr = String::from("b'");
+                push_escaped(&mut repr, byte_unit(lit.value()), '\'', unicode);
+                repr.push('\'');
+                repr
+            }
+            None => lit.token().to_string(),
+        };
+        self.word(repr);
     }
 
     fn lit_char(&mut self, lit: &LitChar) {
-        self.word(lit.token().to_string());
+        let repr = match self.escapes(lit.suffix()) {
+            Some(unicode) => {
+                let mut repr = String::from("'");
+                push_escaped(&mut repr, Ok(lit.value()), '\'', unicode);
+                repr.push('\'');
+                repr
+            }
+            None => lit.token().to_string(),
+        };
+        self.word(repr);
+    }
+
+    // Whether to render a string or character literal from its value, and if
+    // so whether to write printable non-ASCII characters unescaped. Literals
+    // with a suffix are left as written.
+    fn escapes(&self, suffix: &str) -> Option<bool> {
+        match self.config.literal_escapes {
+            _ if !suffix.is_empty() => None,
+            LiteralEscapes::Preserve => None,
+            LiteralEscapes::Ascii => Some(false),
+            LiteralEscapes::Unicode => Some(true),
+        }
     }
 
     fn lit_int(&mut self, lit: &LitInt) {
-        self.word(lit.token().to_string());
+        let repr = lit.token().to_string();
+        self.word(self.numeric_literal(repr, lit.suffix()));
+    }
+
+    // A decimal integer literal of at least 256 whose set bits are contiguous,
+    // written as hexadecimal for `hex_bitmask_consts`. A single set bit is more
+    // likely a size or a count than a mask, and stays decimal.
+    pub fn bitmask_hex(&self, lit: &LitInt) -> Option<String> {
+        let repr = lit.token().to_string();
+        if repr.starts_with("0x") || repr.starts_with("0o") || repr.starts_with("0b") {
+            return None;
+        }
+        let value = lit.base10_digits().parse::<u128>().ok()?;
+        if value < 256 {
+            return None;
+        }
+        let ones = value >> value.trailing_zeros();
+        if ones == 1 || ones & ones.wrapping_add(1) != 0 {
+            return None;
+        }
+        let body = &repr[..repr.len() - lit.suffix().len()];
+        let separator = &body[body.trim_end_matches('_').len()..];
+        let suffix = lit.suffix();
+        let hex = format!("0x{value:x}{separator}{suffix}");
+        Some(self.numeric_literal(hex, lit.suffix()))
     }
 
     fn lit_float(&mut self, lit: &LitFloat) {
-        self.word(lit.token().to_string());
+        let repr = lit.token().to_string();
+        self.word(self.numeric_literal(repr, lit.suffix()));
+    }
+
+    // Rewrites the digits, letters and suffix separator of an integer or float
+    // literal as configured, keeping its value.
+    fn numeric_literal(&self, repr: String, suffix: &str) -> String {
+        if self.config.digit_grouping.is_none()
+            && self.config.literal_case == LiteralCase::Preserve
+            && self.config.literal_suffix == LiteralSuffix::Preserve
+        {
+            return repr;
+        }
+
+        let body = &repr[..repr.len() - suffix.len()];
+        let trimmed = body.trim_end_matches('_');
+        let separator = match self.config.literal_suffix {
+            LiteralSuffix::Preserve => &body[trimmed.len()..],
+            LiteralSuffix::Attached => "",
+            LiteralSuffix::Separated if suffix.is_empty() => "",
+            LiteralSuffix::Separated => "_",
+        };
+        let (prefix, digits) = match trimmed.get(..2) {
+            Some(prefix @ ("0x" | "0o" | "0b")) => (prefix, &trimmed[2..]),
+            _ => ("", trimmed),
+        };
+
+        let mut digits = match (self.config.literal_case, prefix) {
+            (LiteralCase::Preserve, _) => digits.to_owned(),
+            (LiteralCase::Upper, "0x") => digits.to_ascii_uppercase(),
+            (LiteralCase::Lower, "0x") => digits.to_ascii_lowercase(),
+            (LiteralCase::Upper | LiteralCase::Lower, _) => digits.replace('E', "e"),
+        };
+
+        if let Some(threshold) = self.config.digit_grouping {
+            digits.retain(|ch| ch != '_');
+            // Only the integer part of a float literal is grouped.
+            let integer_len = match prefix {
+                "" => digits.find(['.', 'e', 'E']).unwrap_or(digits.len()),
+                _ => digits.len(),
+            };
+            if integer_len > threshold {
+                let group = match prefix {
+                    "0x" | "0b" => 4,
+                    _ => 3,
+                };
+                let mut grouped = String::new();
+                for (i, ch) in digits[..integer_len].chars().enumerate() {
+                    if i > 0 && (integer_len - i) % group == 0 {
+                        grouped.push('_');
+                    }
+                    grouped.push(ch);
+                }
+                grouped.push_str(&digits[integer_len..]);
+                digits = grouped;
+            }
+        }
+
+        format!("{prefix}{digits}{separator}{suffix}")
     }
 
     fn lit_bool(&mut self, lit: &LitBool) {
@@ -55,3 +210,131 @@ impl Printer {
         self.word(token.to_string());
     }
 }
+
+// Splits a non-raw string literal after each run of spaces that is followed
+// by something other than whitespace, which a line continuation would skip.
+// Line continuations already in the literal are removed first, so that its
+// pieces depend only on its value. Returns None for raw strings, strings with
+// a suffix, and strings containing a line break.
+fn string_pieces(repr: &str) -> Option<Vec<String>> {
+    let body = repr.strip_prefix('"')?.strip_suffix('"')?;
+    let mut chars = body.chars().peekable();
+    let mut pieces = Vec::new();
+    let mut piece = String::from('"');
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next()? {
+                '\n' | '\r' => {
+                    while chars
+                        .next_if(|ch| is_continuation_whitespace(*ch))
+                        .is_some()
+                    {}
+                }
+                escaped => {
+                    piece.push('\\');
+                    piece.push(escaped);
+                }
+            },
+            '\n' | '\r' => return None,
+            ' ' => {
+                piece.push(' ');
+                if chars
+                    .peek()
+                    .is_some_and(|next| !is_continuation_whitespace(*next))
+                {
+                    pieces.push(piece);
+                    piece = String::new();
+                }
+            }
+            _ => piece.push(ch),
+        }
+    }
+    piece.push('"');
+    pieces.push(piece);
+    Some(pieces)
+}
+
+// Whitespace skipped after a `\` at the end of a line in a string literal.
+fn is_continuation_whitespace(ch: char) -> bool {
+    matches!(ch, ' ' | '\t' | '\n' | '\r')
+}
+
+// A character of a literal's value, or a byte of a byte string or C string
+// that is not ASCII and can only be written as an escape.
+type Unit = Result<char, u8>;
+
+fn byte_unit(byte: u8) -> Unit {
+    if byte.is_ascii() {
+        Ok(byte as char)
+    } else {
+        Err(byte)
+    }
+}
+
+// Renders a string, byte string or C string literal from its value, escaping
+// only what has to be escaped, or as a raw string if that is shorter.
+fn string_repr(prefix: &str, units: impl Iterator<Item = Unit>, unicode: bool) -> String {
+    let mut escaped = String::new();
+    let mut raw = Some(String::new());
+    let mut quotes_and_backslashes = 0;
+    for unit in units {
+        let len = escaped.len();
+        push_escaped(&mut escaped, unit, '"', unicode);
+        match unit {
+            Ok(ch @ ('"' | '\\')) => {
+                quotes_and_backslashes += 1;
+                if let Some(raw) = &mut raw {
+                    raw.push(ch);
+                }
+            }
+            _ if escaped.len() - len == 1 => {
+                if let Some(raw) = &mut raw {
+                    raw.push_str(&escaped[len..]);
+                }
+            }
+            // Characters that need an escape cannot be written in a raw string.
+            _ => raw = None,
+        }
+    }
+
+    if let Some(raw) = raw {
+        // A raw string ends at the first `"` followed by as many `#` as it
+        // started with.
+        let hashes = raw
+            .match_indices('"')
+            .map(|(i, _quote)| raw[i + 1..].len() - raw[i + 1..].trim_start_matches('#').len() + 1)
+            .max()
+            .unwrap_or(0);
+        if quotes_and_backslashes > 1 + 2 * hashes {
+            let hashes = "#".repeat(hashes);
+            return format!("{prefix}r{hashes}\"{raw}\"{hashes}");
+        }
+    }
+    format!("{prefix}\"{escaped}\"")
+}
+
+fn push_escaped(repr: &mut String, unit: Unit, quote: char, unicode: bool) {
+    match unit {
+        Ok(ch) if ch == quote || ch == '\\' => {
+            repr.push('\\');
+            repr.push(ch);
+        }
+        Ok('\n') => repr.push_str("\\n"),
+        Ok('\r') => repr.push_str("\\r"),
+        Ok('\t') => repr.push_str("\\t"),
+        Ok('\0') => repr.push_str("\\0"),
+        Ok(ch @ ' '..='~') => repr.push(ch),
+        Ok(ch) if ch.is_ascii() => {
+            let _ = write!(repr, "\\x{:02x}", ch as u32);
+        }
+        // Printable characters are exactly those that escape_debug leaves
+        // alone, apart from quotes and backslashes handled above.
+        Ok(ch) if unicode && ch.escape_debug().count() == 1 => repr.push(ch),
+        Ok(ch) => {
+            let _ = write!(repr, "\\u{{{:x}}}", ch as u32);
+        }
+        Err(byte) => {
+            let _ = write!(repr, "\\x{byte:02x}");
+        }
+    }
+}
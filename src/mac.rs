@@ -1,22 +1,49 @@
 use crate::algorithm::Printer;
 use crate::path::PathKind;
 use crate::token::Token;
-use crate::INDENT;
-use proc_macro2::{Delimiter, Spacing, TokenStream};
+use proc_macro2::extra::DelimSpan;
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+use std::iter;
 use syn::{Ident, Macro, MacroDelimiter};
 
-impl Printer {
-    pub fn mac(&mut self, mac: &Macro, ident: Option<&Ident>, semicolon: bool) {
+// Where a macro invocation appears. In item and statement position, a
+// brace-delimited invocation is not followed by a semicolon, which it would
+// need if it were printed with parentheses or brackets.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum MacroPosition {
+    Item,
+    Stmt,
+    Expr,
+    Type,
+}
+
+impl Printer<'_> {
+    pub fn mac(
+        &mut self,
+        mac: &Macro,
+        ident: Option<&Ident>,
+        semicolon: bool,
+        position: MacroPosition,
+    ) {
         if mac.path.is_ident("macro_rules") {
             if let Some(ident) = ident {
-                self.macro_rules(ident, &mac.tokens);
+                self.macro_rules(ident, &mac.tokens, *mac.delimiter.span());
                 return;
             }
         }
         #[cfg(feature = "verbatim")]
-        if ident.is_none() && self.standard_library_macro(mac, semicolon) {
+        if ident.is_none() && self.macro_rules_repetition(mac, semicolon) {
+            return;
+        }
+        #[cfg(feature = "verbatim")]
+        if ident.is_none()
+            && (self.standard_library_macro(mac, semicolon, position)
+                || self.unknown_macro(mac, semicolon))
+        {
             return;
         }
+        #[cfg(not(feature = "verbatim"))]
+        let _ = position;
         self.path(&mac.path, PathKind::Simple);
         self.word("!");
         if let Some(ident) = ident {
@@ -30,13 +57,13 @@ impl Printer {
         };
         self.word(open);
         if !mac.tokens.is_empty() {
-            self.cbox(INDENT);
+            self.cbox(self.indent_unit);
             delimiter_break(self);
             self.ibox(0);
             self.macro_rules_tokens(mac.tokens.clone(), false);
             self.end();
             delimiter_break(self);
-            self.offset(-INDENT);
+            self.offset(-self.indent_unit);
             self.end();
         }
         self.word(close);
@@ -45,7 +72,19 @@ impl Printer {
         }
     }
 
-    fn macro_rules(&mut self, name: &Ident, rules: &TokenStream) {
+    fn macro_rules(&mut self, name: &Ident, rules: &TokenStream, delim: DelimSpan) {
+        self.word("macro_rules! ");
+        self.ident(name);
+        self.word(" {");
+        self.macro_rules_body(rules, ';', delim);
+        self.word("}");
+    }
+
+    // The rules of a `macro_rules!` definition, or of a `macro` definition
+    // with more than one rule, which are terminated by `;` and `,`
+    // respectively. Comments between the rules stay inside the braces
+    // delimited by `delim`.
+    pub fn macro_rules_body(&mut self, rules: &TokenStream, separator: char, delim: DelimSpan) {
         enum State {
             Start,
             Matcher,
@@ -56,25 +95,24 @@ impl Printer {
 
         use State::*;
 
-        self.word("macro_rules! ");
-        self.ident(name);
-        self.word(" {");
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.hardbreak_if_nonempty();
         let mut state = State::Start;
-        for tt in rules.clone() {
-            let token = Token::from(tt);
+        let mut tokens = rules.clone().into_iter();
+        while let Some(tt) = tokens.next() {
+            let token = Token::from(tt.clone());
             match (state, token) {
                 (Start, Token::Group(delimiter, stream)) => {
+                    self.leading_comments(&tt);
                     self.delimiter_open(delimiter);
                     if !stream.is_empty() {
-                        self.cbox(INDENT);
+                        self.cbox(self.indent_unit);
                         self.zerobreak();
                         self.ibox(0);
                         self.macro_rules_tokens(stream, true);
                         self.end();
                         self.zerobreak();
-                        self.offset(-INDENT);
+                        self.offset(-self.indent_unit);
                         self.end();
                     }
                     self.delimiter_close(delimiter);
@@ -92,37 +130,62 @@ impl Printer {
                     self.word(" {");
                     self.neverbreak();
                     if !stream.is_empty() {
-                        self.cbox(INDENT);
+                        self.cbox(self.indent_unit);
                         self.hardbreak();
-                        self.ibox(0);
-                        self.macro_rules_tokens(stream, false);
-                        self.end();
-                        self.hardbreak();
-                        self.offset(-INDENT);
+                        let TokenTree::Group(group) = tt else {
+                            unreachable!();
+                        };
+                        if !self.macro_rules_transcriber(&stream, group.delim_span()) {
+                            self.ibox(0);
+                            self.macro_rules_tokens(stream, false);
+                            self.end();
+                            self.hardbreak();
+                        }
+                        self.offset(-self.indent_unit);
                         self.end();
                     }
                     self.word("}");
                     state = Expander;
                 }
-                (Expander, Token::Punct(';', Spacing::Alone)) => {
-                    self.word(";");
+                (Expander, Token::Punct(ch, Spacing::Alone)) if ch == separator => {
+                    self.word(separator.to_string());
                     self.hardbreak();
                     state = Start;
                 }
-                _ => unimplemented!("bad macro_rules syntax"),
+                (previous, _) => {
+                    if !matches!(previous, Start) {
+                        self.nbsp();
+                    }
+                    // The fallback prints only the tokens not printed yet,
+                    // while an error reports the whole invocation.
+                    if self.config.raw_token_fallback {
+                        let rest: TokenStream = iter::once(tt).chain(tokens).collect();
+                        self.unsupported("macro_rules", &rest);
+                    } else {
+                        self.unsupported("macro_rules", rules);
+                    }
+                    state = Matcher;
+                    break;
+                }
             }
         }
         match state {
             Start => {}
             Expander => {
-                self.word(";");
+                self.word(separator.to_string());
                 self.hardbreak();
             }
             _ => self.hardbreak(),
         }
-        self.offset(-INDENT);
+        self.trailing_comments(delim);
+        self.offset(-self.indent_unit);
         self.end();
-        self.word("}");
+    }
+
+    #[cfg(not(feature = "verbatim"))]
+    fn macro_rules_transcriber(&mut self, stream: &TokenStream, span: DelimSpan) -> bool {
+        let _ = (stream, span);
+        false
     }
 
     pub fn macro_rules_tokens(&mut self, stream: TokenStream, matcher: bool) {
@@ -214,6 +277,35 @@ impl Printer {
     }
 }
 
+// Whether the body of a `macro` definition without parenthesized arguments
+// consists of rules `(...) => {...}` separated by commas.
+#[cfg(feature = "verbatim")]
+pub(crate) fn is_macro2_rules(body: &TokenStream) -> bool {
+    let mut tokens = body.clone().into_iter().peekable();
+    while tokens.peek().is_some() {
+        match (tokens.next(), tokens.next(), tokens.next(), tokens.next()) {
+            (
+                Some(TokenTree::Group(matcher)),
+                Some(TokenTree::Punct(eq)),
+                Some(TokenTree::Punct(gt)),
+                Some(TokenTree::Group(transcriber)),
+            ) if matcher.delimiter() != Delimiter::None
+                && eq.as_char() == '='
+                && eq.spacing() == Spacing::Joint
+                && gt.as_char() == '>'
+                && transcriber.delimiter() != Delimiter::None => {}
+            _ => return false,
+        }
+        match tokens.next() {
+            None => {}
+            Some(TokenTree::Punct(comma))
+                if comma.as_char() == ',' && comma.spacing() == Spacing::Alone => {}
+            Some(_) => return false,
+        }
+    }
+    true
+}
+
 pub(crate) fn requires_semi(delimiter: &MacroDelimiter) -> bool {
     match delimiter {
         MacroDelimiter::Paren(_) | MacroDelimiter::Bracket(_) => true,
@@ -231,30 +323,46 @@ fn is_keyword(ident: &Ident) -> bool {
     }
 }
 
+// Prefix of the placeholder identifiers that stand for the metavariables of a
+// macro_rules transcriber while it is being formatted.
+#[cfg(feature = "verbatim")]
+pub const METAVARIABLE: &str = "__prettyplease_metavariable_";
+
 #[cfg(feature = "verbatim")]
 mod standard_library {
+    use super::{MacroPosition, METAVARIABLE};
     use crate::algorithm::Printer;
+    use crate::config::{Config, LiteralCase, LiteralEscapes, LiteralSuffix, MacroArgs};
     use crate::expr;
     use crate::fixup::FixupContext;
     use crate::iter::IterDelimited;
     use crate::path::PathKind;
-    use crate::INDENT;
+    use proc_macro2::extra::DelimSpan;
+    use proc_macro2::{Delimiter, Group, Punct, Spacing, TokenStream, TokenTree};
+    use std::mem;
     use syn::ext::IdentExt;
     use syn::parse::{Parse, ParseStream, Parser, Result};
     use syn::punctuated::Punctuated;
     use syn::{
-        parenthesized, token, Attribute, Expr, ExprAssign, ExprPath, Ident, Lit, Macro, Pat, Path,
-        Token, Type, Visibility,
+        parenthesized, token, Attribute, Block, Expr, ExprAssign, ExprPath, Ident, Item, Lit,
+        Macro, MacroDelimiter, Pat, Path, Token, Type, Visibility,
     };
 
+    // Prefix of the names of the placeholder macro invocations that stand for
+    // the repetitions of a macro_rules transcriber while it is being formatted.
+    const REPETITION: &str = "__prettyplease_repetition_";
+
     enum KnownMacro {
         Expr(Expr),
         Exprs(Vec<Expr>),
         Cfg(Cfg),
-        Matches(Matches),
+        Matches(Box<Matches>),
         ThreadLocal(Vec<ThreadLocal>),
         VecArray(Punctuated<Expr, Token![,]>),
-        VecRepeat { elem: Expr, n: Expr },
+        VecRepeat { elem: Box<Expr>, n: Box<Expr> },
+        Items(Vec<Item>),
+        Block(Block),
+        Args(Punctuated<Expr, Token![,]>),
     }
 
     enum Cfg {
@@ -435,11 +543,11 @@ mod standard_library {
                 None
             };
             input.parse::<Option<Token![,]>>()?;
-            Ok(KnownMacro::Matches(Matches {
+            Ok(KnownMacro::Matches(Box::new(Matches {
                 expression,
                 pattern,
                 guard,
-            }))
+            })))
         }
 
         fn parse_thread_local(input: ParseStream) -> Result<Self> {
@@ -476,8 +584,8 @@ mod standard_library {
             if input.parse::<Option<Token![;]>>()?.is_some() {
                 let len: Expr = input.parse()?;
                 Ok(KnownMacro::VecRepeat {
-                    elem: first,
-                    n: len,
+                    elem: Box::new(first),
+                    n: Box::new(len),
                 })
             } else {
                 let mut vec = Punctuated::new();
@@ -495,6 +603,37 @@ mod standard_library {
             }
         }
 
+        fn parse_args(input: ParseStream) -> Result<Self> {
+            let args = Punctuated::parse_terminated(input)?;
+            Ok(KnownMacro::Args(args))
+        }
+
+        fn parse_repeat(input: ParseStream) -> Result<Self> {
+            let elem: Expr = input.parse()?;
+            input.parse::<Token![;]>()?;
+            let n: Expr = input.parse()?;
+            Ok(KnownMacro::VecRepeat {
+                elem: Box::new(elem),
+                n: Box::new(n),
+            })
+        }
+
+        fn parse_items(input: ParseStream) -> Result<Self> {
+            let mut items = Vec::new();
+            while !input.is_empty() {
+                items.push(input.parse()?);
+            }
+            Ok(KnownMacro::Items(items))
+        }
+
+        fn parse_block(input: ParseStream) -> Result<Self> {
+            let stmts = input.call(Block::parse_within)?;
+            Ok(KnownMacro::Block(Block {
+                brace_token: token::Brace::default(),
+                stmts,
+            }))
+        }
+
         fn parse_write(input: ParseStream) -> Result<Self> {
             let mut exprs = Vec::new();
             let dst: Expr = input.parse()?;
@@ -519,59 +658,84 @@ mod standard_library {
         }
     }
 
-    impl Printer {
-        pub fn standard_library_macro(&mut self, mac: &Macro, mut semicolon: bool) -> bool {
-            let name = mac.path.segments.last().unwrap().ident.to_string();
-            let parser = match name.as_str() {
-                "addr_of" | "addr_of_mut" => KnownMacro::parse_expr,
-                "assert" | "debug_assert" => KnownMacro::parse_assert,
-                "assert_eq" | "assert_ne" | "debug_assert_eq" | "debug_assert_ne" => {
-                    KnownMacro::parse_assert_cmp
-                }
-                "cfg" => KnownMacro::parse_cfg,
-                "compile_error" | "include" | "include_bytes" | "include_str" | "option_env" => {
-                    KnownMacro::parse_expr_comma
-                }
-                "concat" | "concat_bytes" | "dbg" => KnownMacro::parse_exprs,
-                "const_format_args" | "eprint" | "eprintln" | "format" | "format_args"
-                | "format_args_nl" | "panic" | "print" | "println" | "todo" | "unimplemented"
-                | "unreachable" => KnownMacro::parse_format_args,
-                "env" => KnownMacro::parse_env,
-                "matches" => KnownMacro::parse_matches,
-                "thread_local" => KnownMacro::parse_thread_local,
-                "vec" => KnownMacro::parse_vec,
-                "write" => KnownMacro::parse_write,
-                "writeln" => KnownMacro::parse_writeln,
-                _ => return false,
+    impl Printer<'_> {
+        pub fn standard_library_macro(
+            &mut self,
+            mac: &Macro,
+            mut semicolon: bool,
+            position: MacroPosition,
+        ) -> bool {
+            let registered = self.config.macros.get(&mac.path);
+            let parser = match registered {
+                Some(MacroArgs::Exprs) => KnownMacro::parse_exprs,
+                Some(MacroArgs::FormatArgs) => KnownMacro::parse_format_args,
+                Some(MacroArgs::Items) => KnownMacro::parse_items,
+                Some(MacroArgs::Block) => KnownMacro::parse_block,
+                None => {
+                    let name = mac.path.segments.last().unwrap().ident.to_string();
+                    match name.as_str() {
+                        "addr_of" | "addr_of_mut" => KnownMacro::parse_expr,
+                        "assert" | "debug_assert" => KnownMacro::parse_assert,
+                        "assert_eq" | "assert_ne" | "debug_assert_eq" | "debug_assert_ne" => {
+                            KnownMacro::parse_assert_cmp
+                        }
+                        "cfg" => KnownMacro::parse_cfg,
+                        "compile_error" | "include" | "include_bytes" | "include_str"
+                        | "option_env" => KnownMacro::parse_expr_comma,
+                        "concat" | "concat_bytes" | "dbg" => KnownMacro::parse_exprs,
+                        "const_format_args" | "eprint" | "eprintln" | "format" | "format_args"
+                        | "format_args_nl" | "panic" | "print" | "println" | "todo"
+                        | "unimplemented" | "unreachable" => KnownMacro::parse_format_args,
+                        "env" => KnownMacro::parse_env,
+                        "matches" => KnownMacro::parse_matches,
+                        "thread_local" => KnownMacro::parse_thread_local,
+                        "vec" => KnownMacro::parse_vec,
+                        "write" => KnownMacro::parse_write,
+                        "writeln" => KnownMacro::parse_writeln,
+                        _ => return false,
+                    }
+                }
             };
 
             let Ok(known_macro) = parser.parse2(mac.tokens.clone()) else {
                 return false;
             };
 
+            if registered.is_some() {
+                self.delimited_macro(mac, known_macro, semicolon);
+                return true;
+            }
+
+            if let MacroDelimiter::Brace(_) = mac.delimiter {
+                let stmt_position = matches!(position, MacroPosition::Item | MacroPosition::Stmt);
+                if stmt_position && !matches!(known_macro, KnownMacro::ThreadLocal(_)) {
+                    return false;
+                }
+            }
+
             self.path(&mac.path, PathKind::Simple);
             self.word("!");
 
             match &known_macro {
                 KnownMacro::Expr(expr) => {
                     self.word("(");
-                    self.cbox(INDENT);
+                    self.cbox(self.indent_unit);
                     self.zerobreak();
                     self.expr(expr, FixupContext::NONE);
                     self.zerobreak();
-                    self.offset(-INDENT);
+                    self.offset(-self.indent_unit);
                     self.end();
                     self.word(")");
                 }
                 KnownMacro::Exprs(exprs) => {
                     self.word("(");
-                    self.cbox(INDENT);
+                    self.cbox(self.indent_unit);
                     self.zerobreak();
                     for elem in exprs.iter().delimited() {
                         self.expr(&elem, FixupContext::NONE);
                         self.trailing_comma(elem.is_last);
                     }
-                    self.offset(-INDENT);
+                    self.offset(-self.indent_unit);
                     self.end();
                     self.word(")");
                 }
@@ -582,7 +746,7 @@ mod standard_library {
                 }
                 KnownMacro::Matches(matches) => {
                     self.word("(");
-                    self.cbox(INDENT);
+                    self.cbox(self.indent_unit);
                     self.zerobreak();
                     self.expr(&matches.expression, FixupContext::NONE);
                     self.word(",");
@@ -594,13 +758,13 @@ mod standard_library {
                         self.expr(guard, FixupContext::NONE);
                     }
                     self.zerobreak();
-                    self.offset(-INDENT);
+                    self.offset(-self.indent_unit);
                     self.end();
                     self.word(")");
                 }
                 KnownMacro::ThreadLocal(items) => {
                     self.word(" {");
-                    self.cbox(INDENT);
+                    self.cbox(self.indent_unit);
                     self.hardbreak_if_nonempty();
                     for item in items {
                         self.outer_attrs(&item.attrs);
@@ -617,7 +781,7 @@ mod standard_library {
                         self.end();
                         self.hardbreak();
                     }
-                    self.offset(-INDENT);
+                    self.offset(-self.indent_unit);
                     self.end();
                     self.word("}");
                     semicolon = false;
@@ -626,7 +790,7 @@ mod standard_library {
                     if vec.is_empty() {
                         self.word("[]");
                     } else if expr::simple_array(vec) {
-                        self.cbox(INDENT);
+                        self.cbox(self.indent_unit);
                         self.word("[");
                         self.zerobreak();
                         self.ibox(0);
@@ -639,35 +803,38 @@ mod standard_library {
                         }
                         self.end();
                         self.trailing_comma(true);
-                        self.offset(-INDENT);
+                        self.offset(-self.indent_unit);
                         self.word("]");
                         self.end();
                     } else {
                         self.word("[");
-                        self.cbox(INDENT);
+                        self.cbox(self.indent_unit);
                         self.zerobreak();
                         for elem in vec.iter().delimited() {
                             self.expr(&elem, FixupContext::NONE);
                             self.trailing_comma(elem.is_last);
                         }
-                        self.offset(-INDENT);
+                        self.offset(-self.indent_unit);
                         self.end();
                         self.word("]");
                     }
                 }
                 KnownMacro::VecRepeat { elem, n } => {
                     self.word("[");
-                    self.cbox(INDENT);
+                    self.cbox(self.indent_unit);
                     self.zerobreak();
                     self.expr(elem, FixupContext::NONE);
                     self.word(";");
                     self.space();
                     self.expr(n, FixupContext::NONE);
                     self.zerobreak();
-                    self.offset(-INDENT);
+                    self.offset(-self.indent_unit);
                     self.end();
                     self.word("]");
                 }
+                KnownMacro::Items(_) | KnownMacro::Block(_) | KnownMacro::Args(_) => {
+                    unreachable!()
+                }
             }
 
             if semicolon {
@@ -677,6 +844,280 @@ mod standard_library {
             true
         }
 
+        // With Config::format_unknown_macros, macros that are not from the
+        // standard library are formatted if their arguments happen to parse as
+        // comma-separated expressions, as an `[expr; len]` repetition inside
+        // brackets, or as statements or items inside braces, in that order of
+        // preference. The printer normalizes some syntax, such as dropping the
+        // leading `|` of a pattern or a trailing comma, which the macro might
+        // not accept, so the formatted arguments are used only if they consist
+        // of exactly the original tokens.
+        pub fn unknown_macro(&mut self, mac: &Macro, semicolon: bool) -> bool {
+            if !self.config.format_unknown_macros || mac.tokens.is_empty() {
+                return false;
+            }
+            let parsers: &[fn(ParseStream) -> Result<KnownMacro>] = match mac.delimiter {
+                MacroDelimiter::Paren(_) => &[KnownMacro::parse_args],
+                MacroDelimiter::Bracket(_) => &[KnownMacro::parse_args, KnownMacro::parse_repeat],
+                MacroDelimiter::Brace(_) => &[
+                    KnownMacro::parse_args,
+                    KnownMacro::parse_block,
+                    KnownMacro::parse_items,
+                ],
+            };
+            // Deciding reprints the arguments, including any macros nested
+            // inside. Remembering each decision keeps deeply nested macros
+            // from being reprinted once per enclosing macro.
+            let delimiter = match mac.delimiter {
+                MacroDelimiter::Paren(_) => '(',
+                MacroDelimiter::Brace(_) => '{',
+                MacroDelimiter::Bracket(_) => '[',
+            };
+            let key = (delimiter, mac.tokens.to_string());
+            let choice = if let Some(&choice) = self.unknown_macros.get(&key) {
+                choice
+            } else {
+                let choice = parsers
+                    .iter()
+                    .position(|parser| self.reprints_unchanged(mac, *parser));
+                self.unknown_macros.insert(key, choice);
+                choice
+            };
+            let Some(parser) = choice.map(|i| parsers[i]) else {
+                return false;
+            };
+            let known_macro = parser.parse2(mac.tokens.clone()).unwrap();
+            let exact_tokens = mem::replace(&mut self.exact_tokens, true);
+            self.delimited_macro(mac, known_macro, semicolon);
+            self.exact_tokens = exact_tokens;
+            true
+        }
+
+        fn reprints_unchanged(
+            &mut self,
+            mac: &Macro,
+            parser: fn(ParseStream) -> Result<KnownMacro>,
+        ) -> bool {
+            let Ok(known_macro) = parser.parse2(mac.tokens.clone()) else {
+                return false;
+            };
+            let mut printer = Printer::new(&token_config(&self.config));
+            printer.exact_tokens = true;
+            printer.unknown_macros = mem::take(&mut self.unknown_macros);
+            printer.delimited_macro(mac, known_macro, false);
+            self.unknown_macros = mem::take(&mut printer.unknown_macros);
+            let Ok(reprinted) = printer.eof() else {
+                return false;
+            };
+            let Ok(tokens) = reprinted.parse::<TokenStream>() else {
+                return false;
+            };
+            match tokens.into_iter().last() {
+                Some(TokenTree::Group(group)) => same_tokens(group.stream(), mac.tokens.clone()),
+                _ => false,
+            }
+        }
+
+        // Formats the transcriber of a macro_rules rule as statements or items,
+        // with each `$var` replaced by a placeholder identifier and each
+        // `$(...) sep op` by a placeholder macro invocation, both of which
+        // print as the original. As for unknown macros, the formatted
+        // transcriber is used only if it consists of exactly the original
+        // tokens.
+        pub fn macro_rules_transcriber(&mut self, stream: &TokenStream, span: DelimSpan) -> bool {
+            if !self.config.format_macro_rules || self.repetitions.is_some() {
+                return false;
+            }
+            let mut repetitions = Vec::new();
+            let Some(substituted) = substitute_metavariables(stream.clone(), &mut repetitions)
+            else {
+                return false;
+            };
+            let parsers: [fn(ParseStream) -> Result<KnownMacro>; 2] =
+                [KnownMacro::parse_block, KnownMacro::parse_items];
+            for parser in parsers {
+                let Ok(known_macro) = parser.parse2(substituted.clone()) else {
+                    continue;
+                };
+                let mut printer = Printer::new(&token_config(&self.config));
+                printer.exact_tokens = true;
+                printer.repetitions = Some(repetitions.clone());
+                printer.cbox(0);
+                printer.transcriber(known_macro, span);
+                printer.end();
+                let Ok(reprinted) = printer.eof() else {
+                    continue;
+                };
+                if reprinted
+                    .parse()
+                    .is_ok_and(|tokens| same_tokens(tokens, stream.clone()))
+                {
+                    let known_macro = parser.parse2(substituted).unwrap();
+                    let exact_tokens = mem::replace(&mut self.exact_tokens, true);
+                    self.repetitions = Some(repetitions);
+                    self.transcriber(known_macro, span);
+                    self.repetitions = None;
+                    self.exact_tokens = exact_tokens;
+                    return true;
+                }
+            }
+            false
+        }
+
+        fn transcriber(&mut self, known_macro: KnownMacro, span: DelimSpan) {
+            match known_macro {
+                KnownMacro::Block(mut block) => {
+                    block.brace_token.span = span;
+                    self.stmts(&block);
+                }
+                KnownMacro::Items(items) => {
+                    self.items(&items);
+                    self.trailing_comments(span);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // Prints a placeholder macro invocation inserted by
+        // macro_rules_transcriber as the repetition it stands for.
+        pub fn macro_rules_repetition(&mut self, mac: &Macro, semicolon: bool) -> bool {
+            let Some(repetitions) = &self.repetitions else {
+                return false;
+            };
+            let Some(suffix) = mac
+                .path
+                .get_ident()
+                .and_then(|ident| ident.to_string().strip_prefix(REPETITION)?.parse().ok())
+                .and_then(|index: usize| repetitions.get(index).cloned())
+            else {
+                return false;
+            };
+            self.word("$(");
+            if let Ok(KnownMacro::Args(args)) = KnownMacro::parse_args.parse2(mac.tokens.clone()) {
+                self.ibox(0);
+                for pair in args.pairs().delimited() {
+                    self.expr(pair.value(), FixupContext::NONE);
+                    if pair.punct().is_some() {
+                        self.word(",");
+                    }
+                    if !pair.is_last {
+                        self.space();
+                    }
+                }
+                self.end();
+            } else if let Ok(KnownMacro::Block(mut block)) =
+                KnownMacro::parse_block.parse2(mac.tokens.clone())
+            {
+                block.brace_token.span = *mac.delimiter.span();
+                self.cbox(self.indent_unit);
+                self.hardbreak();
+                self.stmts(&block);
+                self.offset(-self.indent_unit);
+                self.end();
+            } else {
+                self.ibox(0);
+                self.macro_rules_tokens(mac.tokens.clone(), false);
+                self.end();
+            }
+            self.word(")");
+            self.word(suffix);
+            if semicolon {
+                self.word(";");
+            }
+            true
+        }
+
+        // Unlike standard library macros, which are printed with their
+        // conventional delimiter, macros declared in Config::macros and
+        // unknown macros keep the delimiter they were invoked with.
+        fn delimited_macro(&mut self, mac: &Macro, known_macro: KnownMacro, semicolon: bool) {
+            self.path(&mac.path, PathKind::Simple);
+            self.word("!");
+            let (open, close) = match mac.delimiter {
+                MacroDelimiter::Paren(_) => ("(", ")"),
+                MacroDelimiter::Brace(_) => (" {", "}"),
+                MacroDelimiter::Bracket(_) => ("[", "]"),
+            };
+            self.word(open);
+            match known_macro {
+                KnownMacro::Exprs(exprs) if !exprs.is_empty() => {
+                    let brace = matches!(mac.delimiter, MacroDelimiter::Brace(_));
+                    self.cbox(self.indent_unit);
+                    if brace {
+                        self.space();
+                    } else {
+                        self.zerobreak();
+                    }
+                    for elem in exprs.iter().delimited() {
+                        self.expr(&elem, FixupContext::NONE);
+                        if brace {
+                            self.trailing_comma_or_space(elem.is_last);
+                        } else {
+                            self.trailing_comma(elem.is_last);
+                        }
+                    }
+                    self.offset(-self.indent_unit);
+                    self.end();
+                }
+                KnownMacro::Args(args) => {
+                    // The macro may not accept a trailing comma, so keep one
+                    // only where the input had one.
+                    let brace = matches!(mac.delimiter, MacroDelimiter::Brace(_));
+                    self.cbox(self.indent_unit);
+                    if brace {
+                        self.space();
+                    } else {
+                        self.zerobreak();
+                    }
+                    for pair in args.pairs().delimited() {
+                        self.expr(pair.value(), FixupContext::NONE);
+                        if pair.punct().is_some() {
+                            self.word(",");
+                        }
+                        if !pair.is_last || brace {
+                            self.space();
+                        } else {
+                            self.zerobreak();
+                        }
+                    }
+                    self.offset(-self.indent_unit);
+                    self.end();
+                }
+                KnownMacro::VecRepeat { elem, n } => {
+                    self.cbox(self.indent_unit);
+                    self.zerobreak();
+                    self.expr(&elem, FixupContext::NONE);
+                    self.word(";");
+                    self.space();
+                    self.expr(&n, FixupContext::NONE);
+                    self.zerobreak();
+                    self.offset(-self.indent_unit);
+                    self.end();
+                }
+                KnownMacro::Items(items) if !items.is_empty() => {
+                    self.cbox(self.indent_unit);
+                    self.hardbreak();
+                    self.items(&items);
+                    self.trailing_comments(*mac.delimiter.span());
+                    self.offset(-self.indent_unit);
+                    self.end();
+                }
+                KnownMacro::Block(mut block) if !block.stmts.is_empty() => {
+                    block.brace_token.span = *mac.delimiter.span();
+                    self.cbox(self.indent_unit);
+                    self.hardbreak();
+                    self.stmts(&block);
+                    self.offset(-self.indent_unit);
+                    self.end();
+                }
+                _ => {}
+            }
+            self.word(close);
+            if semicolon {
+                self.word(";");
+            }
+        }
+
         fn cfg(&mut self, cfg: &Cfg) {
             match cfg {
                 Cfg::Eq(ident, value) => {
@@ -689,17 +1130,110 @@ mod standard_library {
                 Cfg::Call(ident, args) => {
                     self.ident(ident);
                     self.word("(");
-                    self.cbox(INDENT);
+                    self.cbox(self.indent_unit);
                     self.zerobreak();
                     for arg in args.iter().delimited() {
                         self.cfg(&arg);
                         self.trailing_comma(arg.is_last);
                     }
-                    self.offset(-INDENT);
+                    self.offset(-self.indent_unit);
                     self.end();
                     self.word(")");
                 }
             }
         }
     }
+
+    // Replaces each `$var` of a macro_rules transcriber by a placeholder
+    // identifier, and each `$(...) sep op` repetition by a placeholder macro
+    // invocation, recording the text that follows the repetition's closing
+    // parenthesis. Returns None if the transcriber contains `$` in some other
+    // form.
+    fn substitute_metavariables(
+        stream: TokenStream,
+        repetitions: &mut Vec<String>,
+    ) -> Option<TokenStream> {
+        let mut tokens = stream.into_iter();
+        let mut substituted = Vec::new();
+        while let Some(tt) = tokens.next() {
+            match tt {
+                TokenTree::Punct(punct) if punct.as_char() == '$' => match tokens.next()? {
+                    TokenTree::Ident(ident) => {
+                        let name = ident.to_string();
+                        if name.starts_with("r#") {
+                            return None;
+                        }
+                        let placeholder = format!("{METAVARIABLE}{name}");
+                        substituted.push(TokenTree::Ident(Ident::new(&placeholder, ident.span())));
+                    }
+                    TokenTree::Group(group) if group.delimiter() == Delimiter::Parenthesis => {
+                        let inner = substitute_metavariables(group.stream(), repetitions)?;
+                        let suffix = match tokens.next()? {
+                            TokenTree::Punct(op) if matches!(op.as_char(), '*' | '+' | '?') => {
+                                op.to_string()
+                            }
+                            separator => match tokens.next()? {
+                                TokenTree::Punct(op) if matches!(op.as_char(), '*' | '+') => {
+                                    match separator {
+                                        TokenTree::Punct(_) => format!("{separator}{op}"),
+                                        _ => format!(" {separator} {op}"),
+                                    }
+                                }
+                                _ => return None,
+                            },
+                        };
+                        let placeholder = format!("{}{}", REPETITION, repetitions.len());
+                        repetitions.push(suffix);
+                        substituted.push(TokenTree::Ident(Ident::new(&placeholder, group.span())));
+                        substituted.push(TokenTree::Punct(Punct::new('!', Spacing::Alone)));
+                        let mut placeholder_group = Group::new(Delimiter::Brace, inner);
+                        placeholder_group.set_span(group.span());
+                        substituted.push(TokenTree::Group(placeholder_group));
+                    }
+                    _ => return None,
+                },
+                TokenTree::Group(group) => {
+                    let inner = substitute_metavariables(group.stream(), repetitions)?;
+                    let mut substituted_group = Group::new(group.delimiter(), inner);
+                    substituted_group.set_span(group.span());
+                    substituted.push(TokenTree::Group(substituted_group));
+                }
+                tt => substituted.push(tt),
+            }
+        }
+        Some(TokenStream::from_iter(substituted))
+    }
+
+    // Configuration for reprinting macro input to compare its tokens against
+    // the original. Doc comments and literals are rewritten only in the final
+    // output, since reflowing them, formatting their code or rewriting their
+    // escapes or digits changes their tokens.
+    fn token_config(config: &Config) -> Config {
+        let mut config = config.clone();
+        config.wrap_doc_comments = false;
+        config.format_doc_code_blocks = false;
+        config.wrap_string_literals = false;
+        config.digit_grouping = None;
+        config.literal_case = LiteralCase::Preserve;
+        config.literal_suffix = LiteralSuffix::Preserve;
+        config.literal_escapes = LiteralEscapes::Preserve;
+        config
+    }
+
+    // Whether two token streams consist of the same tokens, disregarding spans
+    // and spacing.
+    fn same_tokens(a: TokenStream, b: TokenStream) -> bool {
+        let a = Vec::from_iter(a);
+        let b = Vec::from_iter(b);
+        a.len() == b.len()
+            && a.into_iter().zip(b).all(|pair| match pair {
+                (TokenTree::Group(a), TokenTree::Group(b)) => {
+                    a.delimiter() == b.delimiter() && same_tokens(a.stream(), b.stream())
+                }
+                (TokenTree::Ident(a), TokenTree::Ident(b)) => a == b,
+                (TokenTree::Punct(a), TokenTree::Punct(b)) => a.as_char() == b.as_char(),
+                (TokenTree::Literal(a), TokenTree::Literal(b)) => a.to_string() == b.to_string(),
+                _ => false,
+            })
+    }
 }
@@ -0,0 +1,129 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: prettyplease [OPTIONS] [FILE]...
+
+Formats Rust source files, or standard input if no FILE is given or FILE is -.
+The formatted code is written to standard output unless --write or --check is
+passed.
+
+Options:
+  -w, --write    Format the files in place
+      --check    Do not write anything; exit with status 1 if any input is not
+                 already formatted
+  -h, --help     Print this message
+  -V, --version  Print the version
+";
+
+enum Mode {
+    Stdout,
+    Write,
+    Check,
+}
+
+fn main() -> ExitCode {
+    let mut mode = Mode::Stdout;
+    let mut paths = Vec::new();
+    let mut options_done = false;
+    for arg in env::args_os().skip(1) {
+        if options_done || arg == "-" || !arg.to_string_lossy().starts_with('-') {
+            paths.push(arg);
+            continue;
+        }
+        match arg.to_str() {
+            Some("-w" | "--write") => mode = Mode::Write,
+            Some("--check") => mode = Mode::Check,
+            Some("-h" | "--help") => {
+                print!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            Some("-V" | "--version") => {
+                println!("prettyplease {}", env!("CARGO_PKG_VERSION"));
+                return ExitCode::SUCCESS;
+            }
+            Some("--") => options_done = true,
+            _ => {
+                eprintln!(
+                    "error: unrecognized option {}\n\n{}",
+                    arg.to_string_lossy(),
+                    USAGE
+                );
+                return ExitCode::from(2);
+            }
+        }
+    }
+    if paths.is_empty() {
+        paths.push(OsString::from("-"));
+    }
+
+    let mut success = true;
+    for path in &paths {
+        let path = Path::new(path);
+        if let Err(message) = process(path, &mode) {
+            eprintln!("{message}");
+            success = false;
+        }
+    }
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn process(path: &Path, mode: &Mode) -> Result<(), String> {
+    let is_stdin = path == Path::new("-");
+    let display = if is_stdin {
+        "<stdin>".into()
+    } else {
+        path.display().to_string()
+    };
+
+    let mut source = String::new();
+    let read = if is_stdin {
+        io::stdin().read_to_string(&mut source).map(drop)
+    } else {
+        fs::read_to_string(path).map(|string| source = string)
+    };
+    read.map_err(|error| format!("error: failed to read {display}: {error}"))?;
+
+    let syntax_tree = syn::parse_file(&source).map_err(|error| {
+        let start = error.span().start();
+        format!(
+            "error: {}:{}:{}: {}",
+            display,
+            start.line,
+            start.column + 1,
+            error,
+        )
+    })?;
+    let config = prettyplease::Config::default();
+    let formatted = prettyplease::try_unparse_with_comments(&syntax_tree, &source, &config)
+        .map_err(|error| format!("error: {display}: {error}"))?;
+
+    match mode {
+        Mode::Stdout => io::stdout()
+            .write_all(formatted.as_bytes())
+            .map_err(|error| format!("error: failed to write output: {error}")),
+        Mode::Write if is_stdin => Err("error: cannot format standard input in place".to_owned()),
+        Mode::Write => {
+            if formatted != source {
+                fs::write(path, formatted)
+                    .map_err(|error| format!("error: failed to write {display}: {error}"))?;
+            }
+            Ok(())
+        }
+        Mode::Check => {
+            if formatted == source {
+                Ok(())
+            } else {
+                Err(format!("{display} is not formatted"))
+            }
+        }
+    }
+}
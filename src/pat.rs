@@ -2,14 +2,13 @@ use crate::algorithm::Printer;
 use crate::fixup::FixupContext;
 use crate::iter::IterDelimited;
 use crate::path::PathKind;
-use crate::INDENT;
 use proc_macro2::TokenStream;
 use syn::{
     FieldPat, Pat, PatIdent, PatOr, PatParen, PatReference, PatRest, PatSlice, PatStruct, PatTuple,
     PatTupleStruct, PatType, PatWild,
 };
 
-impl Printer {
+impl Printer<'_> {
     pub fn pat(&mut self, pat: &Pat) {
         match pat {
             #![cfg_attr(all(test, exhaustive), deny(non_exhaustive_omitted_patterns))]
@@ -109,7 +108,7 @@ impl Printer {
 
     fn pat_struct(&mut self, pat: &PatStruct) {
         self.outer_attrs(&pat.attrs);
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.path(&pat.path, PathKind::Expr);
         self.word(" {");
         self.space_if_nonempty();
@@ -121,7 +120,7 @@ impl Printer {
             self.pat_rest(rest);
             self.space();
         }
-        self.offset(-INDENT);
+        self.offset(-self.indent_unit);
         self.end();
         self.word("}");
     }
@@ -129,7 +128,7 @@ impl Printer {
     fn pat_tuple(&mut self, pat: &PatTuple) {
         self.outer_attrs(&pat.attrs);
         self.word("(");
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.zerobreak();
         for elem in pat.elems.iter().delimited() {
             self.pat(&elem);
@@ -142,7 +141,7 @@ impl Printer {
                 self.trailing_comma(elem.is_last);
             }
         }
-        self.offset(-INDENT);
+        self.offset(-self.indent_unit);
         self.end();
         self.word(")");
     }
@@ -151,13 +150,13 @@ impl Printer {
         self.outer_attrs(&pat.attrs);
         self.path(&pat.path, PathKind::Expr);
         self.word("(");
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.zerobreak();
         for elem in pat.elems.iter().delimited() {
             self.pat(&elem);
             self.trailing_comma(elem.is_last);
         }
-        self.offset(-INDENT);
+        self.offset(-self.indent_unit);
         self.end();
         self.word(")");
     }
@@ -171,7 +170,7 @@ impl Printer {
 
     #[cfg(not(feature = "verbatim"))]
     fn pat_verbatim(&mut self, pat: &TokenStream) {
-        unimplemented!("Pat::Verbatim `{}`", pat);
+        self.unsupported("Pat::Verbatim", pat);
     }
 
     #[cfg(feature = "verbatim")]
@@ -218,7 +217,7 @@ impl Printer {
 
         let pat: PatVerbatim = match syn::parse2(tokens.clone()) {
             Ok(pat) => pat,
-            Err(_) => unimplemented!("Pat::Verbatim `{}`", tokens),
+            Err(_) => return self.unsupported("Pat::Verbatim", tokens),
         };
 
         match pat {
@@ -231,7 +230,7 @@ impl Printer {
             }
             PatVerbatim::Const(pat) => {
                 self.word("const ");
-                self.cbox(INDENT);
+                self.cbox(self.indent_unit);
                 self.small_block(&pat.block, &pat.attrs);
                 self.end();
             }
@@ -1,6 +1,5 @@
 use crate::algorithm::Printer;
 use crate::iter::IterDelimited;
-use crate::INDENT;
 use std::ptr;
 use syn::{
     AngleBracketedGenericArguments, AssocConst, AssocType, Constraint, GenericArgument,
@@ -17,7 +16,7 @@ pub enum PathKind {
     Expr,
 }
 
-impl Printer {
+impl Printer<'_> {
     pub fn path(&mut self, path: &Path, kind: PathKind) {
         assert!(!path.segments.is_empty());
         for segment in path.segments.iter().delimited() {
@@ -71,7 +70,7 @@ impl Printer {
             self.word("::");
         }
         self.word("<");
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.zerobreak();
 
         // Print lifetimes before types/consts/bindings, regardless of their
@@ -103,7 +102,7 @@ impl Printer {
             }
         }
 
-        self.offset(-INDENT);
+        self.offset(-self.indent_unit);
         self.end();
         self.word(">");
     }
@@ -131,7 +130,7 @@ impl Printer {
         if let Some(generics) = &constraint.generics {
             self.angle_bracketed_generic_arguments(generics, PathKind::Type);
         }
-        self.ibox(INDENT);
+        self.ibox(self.indent_unit);
         for bound in constraint.bounds.iter().delimited() {
             if bound.is_first {
                 self.word(": ");
@@ -145,14 +144,14 @@ impl Printer {
     }
 
     fn parenthesized_generic_arguments(&mut self, arguments: &ParenthesizedGenericArguments) {
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.word("(");
         self.zerobreak();
         for ty in arguments.inputs.iter().delimited() {
             self.ty(&ty);
             self.trailing_comma(ty.is_last);
         }
-        self.offset(-INDENT);
+        self.offset(-self.indent_unit);
         self.word(")");
         self.return_type(&arguments.output);
         self.end();
@@ -0,0 +1,155 @@
+use crate::algorithm::Printer;
+#[cfg(feature = "span-locations")]
+use crate::span::{self, Locate};
+#[cfg(feature = "span-locations")]
+use proc_macro2::Span;
+#[cfg(feature = "span-locations")]
+use std::ops::Range;
+
+/// Correspondence between the formatted output of
+/// [`unparse_with_source_map`][crate::unparse_with_source_map] and the syntax
+/// tree nodes that produced it.
+#[cfg(feature = "span-locations")]
+#[derive(Clone, Debug)]
+pub struct SourceMap {
+    mappings: Vec<Mapping>,
+}
+
+/// The output location of one item, statement or expression.
+#[cfg(feature = "span-locations")]
+#[derive(Clone, Debug)]
+pub struct Mapping {
+    kind: NodeKind,
+    span: Span,
+    output: Range<usize>,
+    output_line: usize,
+    // Address of the node, identifying it within the syntax tree that was
+    // printed.
+    #[cfg_attr(not(feature = "verify"), allow(dead_code))]
+    node: usize,
+}
+
+/// The kind of syntax tree node described by a [`Mapping`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NodeKind {
+    /// A `syn::Item`.
+    Item,
+    /// A `syn::Stmt`.
+    Stmt,
+    /// A `syn::Expr`.
+    Expr,
+}
+
+#[cfg(feature = "span-locations")]
+impl SourceMap {
+    /// All recorded nodes in order of their start in the output. A node comes
+    /// before the nodes nested inside of it.
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    /// The innermost node whose output contains the byte at `offset`.
+    pub fn find(&self, offset: usize) -> Option<&Mapping> {
+        self.mappings
+            .iter()
+            .rev()
+            .find(|mapping| mapping.output.contains(&offset))
+    }
+}
+
+#[cfg(feature = "span-locations")]
+impl Mapping {
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    /// The span of the node in the syntax tree that was printed.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Byte range of the node's text in the output.
+    pub fn output_range(&self) -> Range<usize> {
+        self.output.clone()
+    }
+
+    /// The 1-based line of the output on which the node's text begins.
+    pub fn output_line(&self) -> usize {
+        self.output_line
+    }
+
+    #[cfg(feature = "verify")]
+    pub(crate) fn node(&self) -> usize {
+        self.node
+    }
+}
+
+// Nodes are delimited by the indices of the first and one past the last string
+// token they scanned. Output offsets of those strings become known only once
+// the pretty-printing algorithm decides where lines break.
+#[cfg(feature = "span-locations")]
+#[derive(Default)]
+pub struct Recorder {
+    nodes: Vec<(NodeKind, Span, usize, Range<usize>)>,
+    open: Vec<usize>,
+    pub strings: Vec<Range<usize>>,
+}
+
+#[cfg(feature = "span-locations")]
+impl Recorder {
+    pub fn finish(self, output: &str) -> SourceMap {
+        let mut mappings = Vec::new();
+        let mut line = 1;
+        let mut line_offset = 0;
+        for (kind, span, node, strings) in self.nodes {
+            if strings.is_empty() {
+                continue;
+            }
+            let start = self.strings[strings.start].start;
+            let end = self.strings[strings.end - 1].end;
+            // Nodes are ordered by start offset, so line numbers are computed
+            // in one pass over the output.
+            line += output[line_offset..start].matches('\n').count();
+            line_offset = start;
+            mappings.push(Mapping {
+                kind,
+                span,
+                output: start..end,
+                output_line: line,
+                node,
+            });
+        }
+        SourceMap { mappings }
+    }
+}
+
+impl Printer<'_> {
+    #[cfg(not(feature = "span-locations"))]
+    pub fn begin_node<T: ?Sized>(&mut self, kind: NodeKind, node: &T) {
+        let _ = (kind, node);
+    }
+
+    #[cfg(feature = "span-locations")]
+    pub fn begin_node<T: Locate + ?Sized>(&mut self, kind: NodeKind, node: &T) {
+        if let Some(recorder) = &mut self.source_map {
+            let first = self.scanned_strings;
+            recorder.open.push(recorder.nodes.len());
+            let address = (node as *const T).cast::<()>() as usize;
+            recorder
+                .nodes
+                .push((kind, span::span(node), address, first..first));
+        }
+    }
+
+    #[cfg(not(feature = "span-locations"))]
+    pub fn end_node(&mut self) {}
+
+    #[cfg(feature = "span-locations")]
+    pub fn end_node(&mut self) {
+        if let Some(recorder) = &mut self.source_map {
+            let i = recorder.open.pop().unwrap();
+            recorder.nodes[i].3.end = self.scanned_strings;
+        }
+    }
+}
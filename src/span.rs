@@ -0,0 +1,492 @@
+use proc_macro2::{Span, TokenStream, TokenTree};
+use syn::spanned::Spanned;
+use syn::{
+    Arm, AttrStyle, Attribute, Block, Expr, Field, FieldValue, File, FnArg, ForeignItem, ImplItem,
+    Item, Label, Macro, Path, QSelf, Signature, Stmt, Token, TraitItem, Variant, Visibility,
+};
+
+// Locates a syntax tree node in the source text from its first and last
+// tokens. This is equivalent to `Spanned::span` for the nodes that the printer
+// looks up while printing, but without rebuilding the node's entire token
+// stream each time.
+pub trait Locate {
+    // A span that begins where the node's first token begins.
+    fn first_span(&self) -> Span;
+    // A span that ends where the node's last token ends.
+    fn last_span(&self) -> Span;
+}
+
+pub fn span<T: Locate + ?Sized>(node: &T) -> Span {
+    let first = node.first_span();
+    first.join(node.last_span()).unwrap_or(first)
+}
+
+impl<T: Locate + ?Sized> Locate for &T {
+    fn first_span(&self) -> Span {
+        (**self).first_span()
+    }
+
+    fn last_span(&self) -> Span {
+        (**self).last_span()
+    }
+}
+
+impl<T: Locate + ?Sized> Locate for Box<T> {
+    fn first_span(&self) -> Span {
+        (**self).first_span()
+    }
+
+    fn last_span(&self) -> Span {
+        (**self).last_span()
+    }
+}
+
+// Nodes that are small enough to be located through their tokens.
+macro_rules! locate_by_tokens {
+    ($($ty:ty)*) => {
+        $(
+            impl Locate for $ty {
+                fn first_span(&self) -> Span {
+                    self.span()
+                }
+
+                fn last_span(&self) -> Span {
+                    self.span()
+                }
+            }
+        )*
+    };
+}
+
+locate_by_tokens!(Field FnArg ForeignItem TokenTree Variant);
+
+impl Locate for File {
+    fn first_span(&self) -> Span {
+        match (self.attrs.first(), self.items.first()) {
+            (Some(attr), _) => attr.pound_token.spans[0],
+            (None, Some(item)) => item.first_span(),
+            (None, None) => Span::call_site(),
+        }
+    }
+
+    fn last_span(&self) -> Span {
+        match (self.attrs.last(), self.items.last()) {
+            (_, Some(item)) => item.last_span(),
+            (Some(attr), None) => attr.bracket_token.span.close(),
+            (None, None) => Span::call_site(),
+        }
+    }
+}
+
+impl Locate for Item {
+    fn first_span(&self) -> Span {
+        let (attrs, vis, first) = match self {
+            Item::Const(item) => (&item.attrs, Some(&item.vis), item.const_token.span),
+            Item::Enum(item) => (&item.attrs, Some(&item.vis), item.enum_token.span),
+            Item::ExternCrate(item) => (&item.attrs, Some(&item.vis), item.extern_token.span),
+            Item::Fn(item) => (&item.attrs, Some(&item.vis), signature(&item.sig)),
+            Item::ForeignMod(item) => {
+                let first = match &item.unsafety {
+                    Some(unsafety) => unsafety.span,
+                    None => item.abi.extern_token.span,
+                };
+                (&item.attrs, None, first)
+            }
+            Item::Impl(item) => {
+                let first = match (&item.defaultness, &item.unsafety) {
+                    (Some(defaultness), _) => defaultness.span,
+                    (None, Some(unsafety)) => unsafety.span,
+                    (None, None) => item.impl_token.span,
+                };
+                (&item.attrs, None, first)
+            }
+            Item::Macro(item) => (&item.attrs, None, path(None, &item.mac.path)),
+            Item::Mod(item) => {
+                let first = match &item.unsafety {
+                    Some(unsafety) => unsafety.span,
+                    None => item.mod_token.span,
+                };
+                (&item.attrs, Some(&item.vis), first)
+            }
+            Item::Static(item) => (&item.attrs, Some(&item.vis), item.static_token.span),
+            Item::Struct(item) => (&item.attrs, Some(&item.vis), item.struct_token.span),
+            Item::Trait(item) => {
+                let first = match (&item.unsafety, &item.auto_token) {
+                    (Some(unsafety), _) => unsafety.span,
+                    (None, Some(auto_token)) => auto_token.span,
+                    (None, None) => item.trait_token.span,
+                };
+                (&item.attrs, Some(&item.vis), first)
+            }
+            Item::TraitAlias(item) => (&item.attrs, Some(&item.vis), item.trait_token.span),
+            Item::Type(item) => (&item.attrs, Some(&item.vis), item.type_token.span),
+            Item::Union(item) => (&item.attrs, Some(&item.vis), item.union_token.span),
+            Item::Use(item) => (&item.attrs, Some(&item.vis), item.use_token.span),
+            Item::Verbatim(tokens) => return first_token(tokens),
+            _ => return self.span(),
+        };
+        outer_attrs(attrs)
+            .or_else(|| visibility(vis?))
+            .unwrap_or(first)
+    }
+
+    fn last_span(&self) -> Span {
+        match self {
+            Item::Const(item) => item.semi_token.spans[0],
+            Item::Enum(item) => item.brace_token.span.close(),
+            Item::ExternCrate(item) => item.semi_token.spans[0],
+            Item::Fn(item) => item.block.brace_token.span.close(),
+            Item::ForeignMod(item) => item.brace_token.span.close(),
+            Item::Impl(item) => item.brace_token.span.close(),
+            Item::Macro(item) => macro_end(&item.mac, item.semi_token.as_ref()),
+            Item::Mod(item) => match (&item.content, &item.semi) {
+                (Some((brace_token, _items)), _) => brace_token.span.close(),
+                (None, Some(semi)) => semi.spans[0],
+                (None, None) => item.ident.span(),
+            },
+            Item::Static(item) => item.semi_token.spans[0],
+            Item::Struct(item) => match &item.semi_token {
+                Some(semi_token) => semi_token.spans[0],
+                None => item.fields.span(),
+            },
+            Item::Trait(item) => item.brace_token.span.close(),
+            Item::TraitAlias(item) => item.semi_token.spans[0],
+            Item::Type(item) => item.semi_token.spans[0],
+            Item::Union(item) => item.fields.brace_token.span.close(),
+            Item::Use(item) => item.semi_token.spans[0],
+            Item::Verbatim(tokens) => last_token(tokens),
+            _ => self.span(),
+        }
+    }
+}
+
+impl Locate for ImplItem {
+    fn first_span(&self) -> Span {
+        let (attrs, vis, defaultness, first) = match self {
+            ImplItem::Const(item) => (
+                &item.attrs,
+                &item.vis,
+                &item.defaultness,
+                item.const_token.span,
+            ),
+            ImplItem::Fn(item) => (
+                &item.attrs,
+                &item.vis,
+                &item.defaultness,
+                signature(&item.sig),
+            ),
+            ImplItem::Type(item) => (
+                &item.attrs,
+                &item.vis,
+                &item.defaultness,
+                item.type_token.span,
+            ),
+            ImplItem::Macro(item) => {
+                return outer_attrs(&item.attrs).unwrap_or_else(|| path(None, &item.mac.path));
+            }
+            ImplItem::Verbatim(tokens) => return first_token(tokens),
+            _ => return self.span(),
+        };
+        outer_attrs(attrs)
+            .or_else(|| visibility(vis))
+            .or_else(|| Some(defaultness.as_ref()?.span))
+            .unwrap_or(first)
+    }
+
+    fn last_span(&self) -> Span {
+        match self {
+            ImplItem::Const(item) => item.semi_token.spans[0],
+            ImplItem::Fn(item) => item.block.brace_token.span.close(),
+            ImplItem::Type(item) => item.semi_token.spans[0],
+            ImplItem::Macro(item) => macro_end(&item.mac, item.semi_token.as_ref()),
+            ImplItem::Verbatim(tokens) => last_token(tokens),
+            _ => self.span(),
+        }
+    }
+}
+
+impl Locate for TraitItem {
+    fn first_span(&self) -> Span {
+        let (attrs, first) = match self {
+            TraitItem::Const(item) => (&item.attrs, item.const_token.span),
+            TraitItem::Fn(item) => (&item.attrs, signature(&item.sig)),
+            TraitItem::Type(item) => (&item.attrs, item.type_token.span),
+            TraitItem::Macro(item) => (&item.attrs, path(None, &item.mac.path)),
+            TraitItem::Verbatim(tokens) => return first_token(tokens),
+            _ => return self.span(),
+        };
+        outer_attrs(attrs).unwrap_or(first)
+    }
+
+    fn last_span(&self) -> Span {
+        match self {
+            TraitItem::Const(item) => item.semi_token.spans[0],
+            TraitItem::Fn(item) => match (&item.default, &item.semi_token) {
+                (Some(block), _) => block.brace_token.span.close(),
+                (None, Some(semi_token)) => semi_token.spans[0],
+                (None, None) => item.sig.span(),
+            },
+            TraitItem::Type(item) => item.semi_token.spans[0],
+            TraitItem::Macro(item) => macro_end(&item.mac, item.semi_token.as_ref()),
+            TraitItem::Verbatim(tokens) => last_token(tokens),
+            _ => self.span(),
+        }
+    }
+}
+
+impl Locate for Stmt {
+    fn first_span(&self) -> Span {
+        match self {
+            Stmt::Local(local) => outer_attrs(&local.attrs).unwrap_or(local.let_token.span),
+            Stmt::Item(item) => item.first_span(),
+            Stmt::Expr(expr, _semi) => expr.first_span(),
+            Stmt::Macro(stmt) => {
+                outer_attrs(&stmt.attrs).unwrap_or_else(|| path(None, &stmt.mac.path))
+            }
+        }
+    }
+
+    fn last_span(&self) -> Span {
+        match self {
+            Stmt::Local(local) => local.semi_token.spans[0],
+            Stmt::Item(item) => item.last_span(),
+            Stmt::Expr(_expr, Some(semi)) => semi.spans[0],
+            Stmt::Expr(expr, None) => expr.last_span(),
+            Stmt::Macro(stmt) => macro_end(&stmt.mac, stmt.semi_token.as_ref()),
+        }
+    }
+}
+
+impl Locate for Expr {
+    fn first_span(&self) -> Span {
+        let (attrs, first) = match self {
+            Expr::Array(expr) => (&expr.attrs, expr.bracket_token.span.open()),
+            Expr::Assign(expr) => (&expr.attrs, expr.left.first_span()),
+            Expr::Async(expr) => (&expr.attrs, expr.async_token.span),
+            Expr::Await(expr) => (&expr.attrs, expr.base.first_span()),
+            Expr::Binary(expr) => (&expr.attrs, expr.left.first_span()),
+            Expr::Block(expr) => (
+                &expr.attrs,
+                labeled(&expr.label, expr.block.brace_token.span.open()),
+            ),
+            Expr::Break(expr) => (&expr.attrs, expr.break_token.span),
+            Expr::Call(expr) => (&expr.attrs, expr.func.first_span()),
+            Expr::Cast(expr) => (&expr.attrs, expr.expr.first_span()),
+            Expr::Closure(expr) => {
+                let first = if let Some(lifetimes) = &expr.lifetimes {
+                    lifetimes.for_token.span
+                } else if let Some(constness) = &expr.constness {
+                    constness.span
+                } else if let Some(movability) = &expr.movability {
+                    movability.span
+                } else if let Some(asyncness) = &expr.asyncness {
+                    asyncness.span
+                } else if let Some(capture) = &expr.capture {
+                    capture.span
+                } else {
+                    expr.or1_token.spans[0]
+                };
+                (&expr.attrs, first)
+            }
+            Expr::Const(expr) => (&expr.attrs, expr.const_token.span),
+            Expr::Continue(expr) => (&expr.attrs, expr.continue_token.span),
+            Expr::Field(expr) => (&expr.attrs, expr.base.first_span()),
+            Expr::ForLoop(expr) => (&expr.attrs, labeled(&expr.label, expr.for_token.span)),
+            Expr::Group(expr) => (&expr.attrs, expr.group_token.span),
+            Expr::If(expr) => (&expr.attrs, expr.if_token.span),
+            Expr::Index(expr) => (&expr.attrs, expr.expr.first_span()),
+            Expr::Infer(expr) => (&expr.attrs, expr.underscore_token.spans[0]),
+            Expr::Let(expr) => (&expr.attrs, expr.let_token.span),
+            Expr::Lit(expr) => (&expr.attrs, expr.lit.span()),
+            Expr::Loop(expr) => (&expr.attrs, labeled(&expr.label, expr.loop_token.span)),
+            Expr::Macro(expr) => (&expr.attrs, path(None, &expr.mac.path)),
+            Expr::Match(expr) => (&expr.attrs, expr.match_token.span),
+            Expr::MethodCall(expr) => (&expr.attrs, expr.receiver.first_span()),
+            Expr::Paren(expr) => (&expr.attrs, expr.paren_token.span.open()),
+            Expr::Path(expr) => (&expr.attrs, path(expr.qself.as_ref(), &expr.path)),
+            Expr::Range(expr) => {
+                let first = match &expr.start {
+                    Some(start) => start.first_span(),
+                    None => expr.limits.span(),
+                };
+                (&expr.attrs, first)
+            }
+            Expr::RawAddr(expr) => (&expr.attrs, expr.and_token.spans[0]),
+            Expr::Reference(expr) => (&expr.attrs, expr.and_token.spans[0]),
+            Expr::Repeat(expr) => (&expr.attrs, expr.bracket_token.span.open()),
+            Expr::Return(expr) => (&expr.attrs, expr.return_token.span),
+            Expr::Struct(expr) => (&expr.attrs, path(expr.qself.as_ref(), &expr.path)),
+            Expr::Try(expr) => (&expr.attrs, expr.expr.first_span()),
+            Expr::TryBlock(expr) => (&expr.attrs, expr.try_token.span),
+            Expr::Tuple(expr) => (&expr.attrs, expr.paren_token.span.open()),
+            Expr::Unary(expr) => (&expr.attrs, expr.op.span()),
+            Expr::Unsafe(expr) => (&expr.attrs, expr.unsafe_token.span),
+            Expr::Verbatim(tokens) => return first_token(tokens),
+            Expr::While(expr) => (&expr.attrs, labeled(&expr.label, expr.while_token.span)),
+            Expr::Yield(expr) => (&expr.attrs, expr.yield_token.span),
+            _ => return self.span(),
+        };
+        outer_attrs(attrs).unwrap_or(first)
+    }
+
+    fn last_span(&self) -> Span {
+        match self {
+            Expr::Array(expr) => expr.bracket_token.span.close(),
+            Expr::Assign(expr) => expr.right.last_span(),
+            Expr::Async(expr) => block(&expr.block),
+            Expr::Await(expr) => expr.await_token.span,
+            Expr::Binary(expr) => expr.right.last_span(),
+            Expr::Block(expr) => block(&expr.block),
+            Expr::Break(expr) => match (&expr.expr, &expr.label) {
+                (Some(value), _) => value.last_span(),
+                (None, Some(label)) => label.ident.span(),
+                (None, None) => expr.break_token.span,
+            },
+            Expr::Call(expr) => expr.paren_token.span.close(),
+            Expr::Cast(expr) => expr.ty.span(),
+            Expr::Closure(expr) => expr.body.last_span(),
+            Expr::Const(expr) => block(&expr.block),
+            Expr::Continue(expr) => match &expr.label {
+                Some(label) => label.ident.span(),
+                None => expr.continue_token.span,
+            },
+            Expr::Field(expr) => expr.member.span(),
+            Expr::ForLoop(expr) => block(&expr.body),
+            Expr::Group(expr) => expr.group_token.span,
+            Expr::If(expr) => match &expr.else_branch {
+                Some((_else_token, else_branch)) => else_branch.last_span(),
+                None => block(&expr.then_branch),
+            },
+            Expr::Index(expr) => expr.bracket_token.span.close(),
+            Expr::Infer(expr) => expr.underscore_token.spans[0],
+            Expr::Let(expr) => expr.expr.last_span(),
+            Expr::Lit(expr) => expr.lit.span(),
+            Expr::Loop(expr) => block(&expr.body),
+            Expr::Macro(expr) => expr.mac.delimiter.span().close(),
+            Expr::Match(expr) => expr.brace_token.span.close(),
+            Expr::MethodCall(expr) => expr.paren_token.span.close(),
+            Expr::Paren(expr) => expr.paren_token.span.close(),
+            Expr::Path(expr) => expr.path.span(),
+            Expr::Range(expr) => match &expr.end {
+                Some(end) => end.last_span(),
+                None => expr.limits.span(),
+            },
+            Expr::RawAddr(expr) => expr.expr.last_span(),
+            Expr::Reference(expr) => expr.expr.last_span(),
+            Expr::Repeat(expr) => expr.bracket_token.span.close(),
+            Expr::Return(expr) => match &expr.expr {
+                Some(value) => value.last_span(),
+                None => expr.return_token.span,
+            },
+            Expr::Struct(expr) => expr.brace_token.span.close(),
+            Expr::Try(expr) => expr.question_token.spans[0],
+            Expr::TryBlock(expr) => block(&expr.block),
+            Expr::Tuple(expr) => expr.paren_token.span.close(),
+            Expr::Unary(expr) => expr.expr.last_span(),
+            Expr::Unsafe(expr) => block(&expr.block),
+            Expr::Verbatim(tokens) => last_token(tokens),
+            Expr::While(expr) => block(&expr.body),
+            Expr::Yield(expr) => match &expr.expr {
+                Some(value) => value.last_span(),
+                None => expr.yield_token.span,
+            },
+            _ => self.span(),
+        }
+    }
+}
+
+impl Locate for Arm {
+    fn first_span(&self) -> Span {
+        outer_attrs(&self.attrs).unwrap_or_else(|| self.pat.span())
+    }
+
+    fn last_span(&self) -> Span {
+        match &self.comma {
+            Some(comma) => comma.spans[0],
+            None => self.body.last_span(),
+        }
+    }
+}
+
+impl Locate for FieldValue {
+    fn first_span(&self) -> Span {
+        outer_attrs(&self.attrs).unwrap_or_else(|| self.member.span())
+    }
+
+    fn last_span(&self) -> Span {
+        self.expr.last_span()
+    }
+}
+
+fn outer_attrs(attrs: &[Attribute]) -> Option<Span> {
+    let attr = attrs
+        .iter()
+        .find(|attr| matches!(attr.style, AttrStyle::Outer))?;
+    Some(attr.pound_token.spans[0])
+}
+
+fn visibility(vis: &Visibility) -> Option<Span> {
+    match vis {
+        Visibility::Public(pub_token) => Some(pub_token.span),
+        Visibility::Restricted(vis) => Some(vis.pub_token.span),
+        Visibility::Inherited => None,
+    }
+}
+
+fn signature(sig: &Signature) -> Span {
+    if let Some(constness) = &sig.constness {
+        constness.span
+    } else if let Some(asyncness) = &sig.asyncness {
+        asyncness.span
+    } else if let Some(unsafety) = &sig.unsafety {
+        unsafety.span
+    } else if let Some(abi) = &sig.abi {
+        abi.extern_token.span
+    } else {
+        sig.fn_token.span
+    }
+}
+
+fn path(qself: Option<&QSelf>, path: &Path) -> Span {
+    if let Some(qself) = qself {
+        qself.lt_token.span
+    } else if let Some(leading_colon) = &path.leading_colon {
+        leading_colon.spans[0]
+    } else if let Some(segment) = path.segments.first() {
+        segment.ident.span()
+    } else {
+        path.span()
+    }
+}
+
+fn labeled(label: &Option<Label>, first: Span) -> Span {
+    match label {
+        Some(label) => label.name.apostrophe,
+        None => first,
+    }
+}
+
+fn block(block: &Block) -> Span {
+    block.brace_token.span.close()
+}
+
+fn macro_end(mac: &Macro, semi_token: Option<&Token![;]>) -> Span {
+    match semi_token {
+        Some(semi_token) => semi_token.spans[0],
+        None => mac.delimiter.span().close(),
+    }
+}
+
+fn first_token(tokens: &TokenStream) -> Span {
+    match tokens.clone().into_iter().next() {
+        Some(token) => token.span(),
+        None => Span::call_site(),
+    }
+}
+
+fn last_token(tokens: &TokenStream) -> Span {
+    match tokens.clone().into_iter().last() {
+        Some(token) => token.span(),
+        None => Span::call_site(),
+    }
+}
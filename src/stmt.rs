@@ -2,12 +2,15 @@ use crate::algorithm::Printer;
 use crate::classify;
 use crate::expr;
 use crate::fixup::FixupContext;
-use crate::mac;
-use crate::INDENT;
-use syn::{BinOp, Expr, Stmt};
+use crate::imports;
+use crate::mac::{self, MacroPosition};
+use crate::source_map::NodeKind;
+use syn::{BinOp, Block, Expr, Item, ItemUse, Stmt};
 
-impl Printer {
+impl Printer<'_> {
     pub fn stmt(&mut self, stmt: &Stmt, is_last: bool) {
+        self.leading_comments(stmt);
+        self.begin_node(NodeKind::Stmt, stmt);
         match stmt {
             Stmt::Local(local) => {
                 self.outer_attrs(&local.attrs);
@@ -28,11 +31,11 @@ impl Printer {
                         self.word("else ");
                         self.end();
                         self.neverbreak();
-                        self.cbox(INDENT);
+                        self.cbox(self.indent_unit);
                         if let Some(expr) = expr::simple_block(diverge) {
                             self.small_block(&expr.block, &[]);
                         } else {
-                            self.expr_as_small_block(diverge, INDENT);
+                            self.expr_as_small_block(diverge, self.indent_unit);
                         }
                     }
                 }
@@ -54,12 +57,8 @@ impl Printer {
                     self.expr_beginning_of_line(expr, false, true, FixupContext::new_stmt());
                 }
             }
+            Stmt::Expr(Expr::Verbatim(tokens), Some(_semi)) if tokens.is_empty() => {}
             Stmt::Expr(expr, Some(_semi)) => {
-                if let Expr::Verbatim(tokens) = expr {
-                    if tokens.is_empty() {
-                        return;
-                    }
-                }
                 self.ibox(0);
                 self.expr_beginning_of_line(expr, false, true, FixupContext::new_stmt());
                 if !remove_semi(expr) {
@@ -72,10 +71,44 @@ impl Printer {
                 self.outer_attrs(&stmt.attrs);
                 let semicolon = stmt.semi_token.is_some()
                     || !is_last && mac::requires_semi(&stmt.mac.delimiter);
-                self.mac(&stmt.mac, None, semicolon);
+                self.mac(&stmt.mac, None, semicolon, MacroPosition::Stmt);
                 self.hardbreak();
             }
         }
+        self.end_node();
+    }
+
+    pub fn stmts(&mut self, block: &Block) {
+        let mut prev = None;
+        let mut rest = block.stmts.as_slice();
+        while let Some(stmt) = rest.first() {
+            if let Some(prev) = prev {
+                self.blank_line_between(prev, stmt);
+            }
+            let uses: Vec<&ItemUse> = if imports::enabled(&self.config) {
+                rest.iter()
+                    .map_while(|stmt| match stmt {
+                        Stmt::Item(Item::Use(item)) => Some(item),
+                        _ => None,
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let len = if uses.is_empty() {
+                self.stmt(stmt, rest.len() == 1);
+                1
+            } else {
+                self.leading_comments(stmt);
+                for import in imports::normalize(&uses, &self.config) {
+                    self.item_use_with_tree(import.item, &import.tree);
+                }
+                uses.len()
+            };
+            prev = Some(&rest[len - 1]);
+            rest = &rest[len..];
+        }
+        self.trailing_comments(block.brace_token.span);
     }
 }
 
@@ -169,7 +202,7 @@ pub fn break_after(expr: &Expr) -> bool {
     true
 }
 
-fn remove_semi(expr: &Expr) -> bool {
+pub fn remove_semi(expr: &Expr) -> bool {
     match expr {
         #![cfg_attr(all(test, exhaustive), deny(non_exhaustive_omitted_patterns))]
         Expr::ForLoop(_) | Expr::While(_) => true,
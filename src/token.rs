@@ -1,7 +1,9 @@
 use crate::algorithm::Printer;
+#[cfg(feature = "verbatim")]
+use crate::mac;
 use proc_macro2::{Delimiter, Ident, Literal, Spacing, TokenStream, TokenTree};
 
-impl Printer {
+impl Printer<'_> {
     pub fn single_token(&mut self, token: Token, group_contents: fn(&mut Self, TokenStream)) {
         match token {
             Token::Group(delimiter, stream) => self.token_group(delimiter, stream, group_contents),
@@ -31,6 +33,13 @@ impl Printer {
     }
 
     pub fn ident(&mut self, ident: &Ident) {
+        #[cfg(feature = "verbatim")]
+        if self.repetitions.is_some() {
+            if let Some(name) = ident.to_string().strip_prefix(mac::METAVARIABLE) {
+                self.word(format!("${name}"));
+                return;
+            }
+        }
         self.word(ident.to_string());
     }
 
@@ -1,8 +1,8 @@
 use crate::algorithm::Printer;
 use crate::fixup::FixupContext;
 use crate::iter::IterDelimited;
+use crate::mac::MacroPosition;
 use crate::path::PathKind;
-use crate::INDENT;
 use proc_macro2::TokenStream;
 use syn::{
     Abi, BareFnArg, BareVariadic, ReturnType, Type, TypeArray, TypeBareFn, TypeGroup,
@@ -10,7 +10,7 @@ use syn::{
     TypeSlice, TypeTraitObject, TypeTuple,
 };
 
-impl Printer {
+impl Printer<'_> {
     pub fn ty(&mut self, ty: &Type) {
         match ty {
             #![cfg_attr(all(test, exhaustive), deny(non_exhaustive_omitted_patterns))]
@@ -52,7 +52,7 @@ impl Printer {
             self.abi(abi);
         }
         self.word("fn(");
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.zerobreak();
         for bare_fn_arg in ty.inputs.iter().delimited() {
             self.bare_fn_arg(&bare_fn_arg);
@@ -62,7 +62,7 @@ impl Printer {
             self.bare_variadic(variadic);
             self.zerobreak();
         }
-        self.offset(-INDENT);
+        self.offset(-self.indent_unit);
         self.end();
         self.word(")");
         self.return_type(&ty.output);
@@ -89,7 +89,7 @@ impl Printer {
 
     fn type_macro(&mut self, ty: &TypeMacro) {
         let semicolon = false;
-        self.mac(&ty.mac, None, semicolon);
+        self.mac(&ty.mac, None, semicolon, MacroPosition::Type);
     }
 
     fn type_never(&mut self, ty: &TypeNever) {
@@ -147,7 +147,7 @@ impl Printer {
 
     fn type_tuple(&mut self, ty: &TypeTuple) {
         self.word("(");
-        self.cbox(INDENT);
+        self.cbox(self.indent_unit);
         self.zerobreak();
         for elem in ty.elems.iter().delimited() {
             self.ty(&elem);
@@ -158,14 +158,14 @@ impl Printer {
                 self.trailing_comma(elem.is_last);
             }
         }
-        self.offset(-INDENT);
+        self.offset(-self.indent_unit);
         self.end();
         self.word(")");
     }
 
     #[cfg(not(feature = "verbatim"))]
     fn type_verbatim(&mut self, ty: &TokenStream) {
-        unimplemented!("Type::Verbatim `{}`", ty);
+        self.unsupported("Type::Verbatim", ty);
     }
 
     #[cfg(feature = "verbatim")]
@@ -179,7 +179,7 @@ impl Printer {
             AnonStruct(AnonStruct),
             AnonUnion(AnonUnion),
             DynStar(DynStar),
-            MutSelf(MutSelf),
+            MutSelf(Box<MutSelf>),
         }
 
         struct AnonStruct {
@@ -224,7 +224,7 @@ impl Printer {
                         let ty: Type = input.parse()?;
                         Some(ty)
                     };
-                    Ok(TypeVerbatim::MutSelf(MutSelf { ty }))
+                    Ok(TypeVerbatim::MutSelf(Box::new(MutSelf { ty })))
                 } else if lookahead.peek(Token![...]) {
                     input.parse::<Token![...]>()?;
                     Ok(TypeVerbatim::Ellipsis)
@@ -236,7 +236,7 @@ impl Printer {
 
         let ty: TypeVerbatim = match syn::parse2(tokens.clone()) {
             Ok(ty) => ty,
-            Err(_) => unimplemented!("Type::Verbatim `{}`", tokens),
+            Err(_) => return self.unsupported("Type::Verbatim", tokens),
         };
 
         match ty {
@@ -244,7 +244,7 @@ impl Printer {
                 self.word("...");
             }
             TypeVerbatim::AnonStruct(ty) => {
-                self.cbox(INDENT);
+                self.cbox(self.indent_unit);
                 self.word("struct {");
                 self.hardbreak_if_nonempty();
                 for field in &ty.fields.named {
@@ -252,12 +252,12 @@ impl Printer {
                     self.word(",");
                     self.hardbreak();
                 }
-                self.offset(-INDENT);
+                self.offset(-self.indent_unit);
                 self.end();
                 self.word("}");
             }
             TypeVerbatim::AnonUnion(ty) => {
-                self.cbox(INDENT);
+                self.cbox(self.indent_unit);
                 self.word("union {");
                 self.hardbreak_if_nonempty();
                 for field in &ty.fields.named {
@@ -265,7 +265,7 @@ impl Printer {
                     self.word(",");
                     self.hardbreak();
                 }
-                self.offset(-INDENT);
+                self.offset(-self.indent_unit);
                 self.end();
                 self.word("}");
             }
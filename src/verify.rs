@@ -0,0 +1,1103 @@
+use crate::algorithm::Printer;
+use crate::attr;
+use crate::config::Config;
+use crate::imports;
+use crate::source_map::{Mapping, NodeKind, Recorder};
+use crate::span;
+use crate::stmt;
+use proc_macro2::{Delimiter, LineColumn, Literal, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Write as _};
+use std::mem;
+use std::ops::Range;
+use syn::visit::{self, Visit};
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+    token, Arm, Block, Expr, ExprBlock, ExprClosure, ExprGroup, ExprMacro, ExprTuple, File, Item,
+    ItemMod, ItemUse, Lit, Pat, PatParen, ReturnType, Stmt, Type, TypeGroup, TypeParen,
+    WhereClause,
+};
+
+/// Error returned by [`verify`][crate::verify()] describing how the formatted
+/// output failed to round trip.
+#[derive(Clone, Debug)]
+pub struct VerifyError {
+    kind: VerifyErrorKind,
+    message: String,
+}
+
+/// The check that failed in [`verify`][crate::verify()].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VerifyErrorKind {
+    /// The syntax tree contains a node that cannot be printed.
+    Unsupported,
+    /// The formatted output is not valid Rust syntax.
+    Reparse,
+    /// The formatted output contains different tokens than the syntax tree it
+    /// was printed from.
+    Tokens,
+    /// Formatting the output a second time produced different text.
+    Idempotence,
+    /// The formatted output parses to a syntax tree with a different
+    /// structure, for example because parentheses required by precedence
+    /// were left out. Only checked by
+    /// [`verify_semantics`][crate::verify_semantics].
+    Semantics,
+}
+
+impl VerifyError {
+    pub fn kind(&self) -> VerifyErrorKind {
+        self.kind
+    }
+
+    fn new(kind: VerifyErrorKind, message: String) -> Self {
+        VerifyError { kind, message }
+    }
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+pub fn check(file: &File, config: &Config, semantics: bool) -> Result<String, VerifyError> {
+    let formatted = crate::try_unparse_with(file, config)
+        .map_err(|error| VerifyError::new(VerifyErrorKind::Unsupported, error.to_string()))?;
+
+    let reparsed = syn::parse_file(&formatted).map_err(|error| {
+        let start = error.span().start();
+        let message = format!(
+            "formatted output does not parse: {}\n--> line {}, column {}\n{}",
+            error,
+            start.line,
+            start.column + 1,
+            formatted
+                .lines()
+                .nth(start.line.wrapping_sub(1))
+                .unwrap_or(""),
+        );
+        VerifyError::new(VerifyErrorKind::Reparse, message)
+    })?;
+
+    let as_printed = as_printed(file, config);
+    let expected = events(as_printed.to_token_stream(), false);
+    let actual = events(reparsed.to_token_stream(), false);
+    let tokens_mismatch = |(i, j)| {
+        let message = format!(
+            "formatted output has different tokens than the syntax tree\n- {}\n+ {}",
+            excerpt(&expected, i),
+            excerpt(&actual, j),
+        );
+        VerifyError::new(VerifyErrorKind::Tokens, message)
+    };
+    let loose = compare(&expected, &actual).map_err(tokens_mismatch)?;
+
+    // Parentheses, braces and punctuation that only one side has are accepted
+    // if both syntax trees have the same structure.
+    if semantics || loose.is_some() {
+        let same_structure = same_structure(
+            &canonical(Node::File(&as_printed)),
+            &canonical(Node::File(&reparsed)),
+        );
+        if !same_structure {
+            if !semantics {
+                return Err(tokens_mismatch(loose.unwrap()));
+            }
+            let message = semantics_mismatch(file, config, &formatted, &reparsed);
+            return Err(VerifyError::new(VerifyErrorKind::Semantics, message));
+        }
+    }
+
+    let reformatted = crate::try_unparse_with(&reparsed, config)
+        .map_err(|error| VerifyError::new(VerifyErrorKind::Unsupported, error.to_string()))?;
+    if reformatted != formatted {
+        let message = format!(
+            "formatting the output again produced different text\n{}",
+            diff(&formatted, &reformatted),
+        );
+        return Err(VerifyError::new(VerifyErrorKind::Idempotence, message));
+    }
+
+    Ok(formatted)
+}
+
+// The syntax tree as it is expected to come out of the printer, accounting for
+// config options that rewrite the tree.
+fn as_printed<'a>(file: &'a File, config: &Config) -> Cow<'a, File> {
+    struct Normalize<'a>(&'a Config);
+
+    impl VisitMut for Normalize<'_> {
+        fn visit_file_mut(&mut self, file: &mut File) {
+            file.items = normalize_uses(&file.items, item_use, |item| item, self.0);
+            visit_mut::visit_file_mut(self, file);
+        }
+
+        fn visit_item_mod_mut(&mut self, item: &mut ItemMod) {
+            if let Some((_brace, items)) = &mut item.content {
+                *items = normalize_uses(items, item_use, |item| item, self.0);
+            }
+            visit_mut::visit_item_mod_mut(self, item);
+        }
+
+        fn visit_block_mut(&mut self, block: &mut Block) {
+            block.stmts = normalize_uses(&block.stmts, stmt_use, Stmt::Item, self.0);
+            visit_mut::visit_block_mut(self, block);
+        }
+    }
+
+    fn item_use(item: &Item) -> Option<&ItemUse> {
+        match item {
+            Item::Use(item) => Some(item),
+            _ => None,
+        }
+    }
+
+    fn stmt_use(stmt: &Stmt) -> Option<&ItemUse> {
+        match stmt {
+            Stmt::Item(item) => item_use(item),
+            _ => None,
+        }
+    }
+
+    if !imports::enabled(config) {
+        return Cow::Borrowed(file);
+    }
+    let mut file = file.clone();
+    Normalize(config).visit_file_mut(&mut file);
+    Cow::Owned(file)
+}
+
+// Replaces each run of consecutive use declarations among `nodes` by its
+// normalized form, as printed by `Printer::items` and `Printer::stmts`.
+fn normalize_uses<T: Clone>(
+    nodes: &[T],
+    as_use: fn(&T) -> Option<&ItemUse>,
+    from_item: fn(Item) -> T,
+    config: &Config,
+) -> Vec<T> {
+    let mut normalized = Vec::new();
+    let mut rest = nodes;
+    while let Some(node) = rest.first() {
+        let uses: Vec<&ItemUse> = rest.iter().map_while(as_use).collect();
+        if uses.is_empty() {
+            normalized.push(node.clone());
+            rest = &rest[1..];
+        } else {
+            for import in imports::normalize(&uses, config) {
+                let mut item = import.item.clone();
+                item.tree = import.tree;
+                normalized.push(from_item(Item::Use(item)));
+            }
+            rest = &rest[uses.len()..];
+        }
+    }
+    normalized
+}
+
+// A node of a syntax tree whose structure is compared by `verify_semantics`.
+#[derive(Copy, Clone)]
+enum Node<'a> {
+    File(&'a File),
+    Item(&'a Item),
+    Stmt(&'a Stmt),
+    Expr(&'a Expr),
+}
+
+impl<'a> Node<'a> {
+    fn kind(self) -> Option<NodeKind> {
+        match self {
+            Node::File(_) => None,
+            Node::Item(_) => Some(NodeKind::Item),
+            Node::Stmt(_) => Some(NodeKind::Stmt),
+            Node::Expr(_) => Some(NodeKind::Expr),
+        }
+    }
+
+    // Matches the addresses recorded in the source map by `begin_node`.
+    fn address(self) -> usize {
+        match self {
+            Node::File(node) => node as *const File as usize,
+            Node::Item(node) => node as *const Item as usize,
+            Node::Stmt(node) => node as *const Stmt as usize,
+            Node::Expr(node) => node as *const Expr as usize,
+        }
+    }
+
+    fn span(self) -> (LineColumn, LineColumn) {
+        let span = match self {
+            Node::File(node) => span::span(node),
+            Node::Item(node) => span::span(node),
+            Node::Stmt(node) => span::span(node),
+            Node::Expr(node) => span::span(node),
+        };
+        (span.start(), span.end())
+    }
+
+    // All items, statements and expressions in this node, in order of their
+    // start.
+    fn descendants(self) -> Vec<Node<'a>> {
+        struct Collect<'a>(Vec<Node<'a>>);
+
+        impl<'a> Visit<'a> for Collect<'a> {
+            fn visit_item(&mut self, item: &'a Item) {
+                self.0.push(Node::Item(item));
+                visit::visit_item(self, item);
+            }
+
+            fn visit_stmt(&mut self, stmt: &'a Stmt) {
+                self.0.push(Node::Stmt(stmt));
+                visit::visit_stmt(self, stmt);
+            }
+
+            fn visit_expr(&mut self, expr: &'a Expr) {
+                self.0.push(Node::Expr(expr));
+                visit::visit_expr(self, expr);
+            }
+        }
+
+        let mut collect = Collect(Vec::new());
+        match self {
+            Node::File(node) => collect.visit_file(node),
+            Node::Item(node) => collect.visit_item(node),
+            Node::Stmt(node) => collect.visit_stmt(node),
+            Node::Expr(node) => collect.visit_expr(node),
+        }
+        collect.0
+    }
+}
+
+// Tokens encoding the structure of a node. Parentheses and invisible groups
+// are removed from every expression, which is then wrapped in an invisible
+// group of its own, so that two nodes produce the same tokens only if their
+// expressions nest the same way. Parentheses around types and patterns,
+// blocks that the printer puts around the body of a match arm or closure, and
+// punctuation that the printer adds or removes are removed too.
+fn canonical(node: Node) -> Vec<Event> {
+    struct Canonicalize;
+
+    impl VisitMut for Canonicalize {
+        fn visit_arm_mut(&mut self, arm: &mut Arm) {
+            unwrap_block(&mut arm.body);
+            arm.comma = None;
+            visit_mut::visit_arm_mut(self, arm);
+        }
+
+        fn visit_block_mut(&mut self, block: &mut Block) {
+            // Empty statements, and semicolons that the printer adds or removes.
+            block.stmts.retain(|stmt| match stmt {
+                Stmt::Expr(Expr::Verbatim(tokens), Some(_semi)) => !tokens.is_empty(),
+                _ => true,
+            });
+            for stmt in &mut block.stmts {
+                if let Stmt::Expr(expr, semi) = stmt {
+                    if stmt::add_semi(expr) || stmt::remove_semi(expr) {
+                        *semi = None;
+                    }
+                }
+            }
+            visit_mut::visit_block_mut(self, block);
+        }
+
+        fn visit_expr_closure_mut(&mut self, closure: &mut ExprClosure) {
+            if let ReturnType::Default = closure.output {
+                unwrap_block(&mut closure.body);
+            }
+            closure.inputs.pop_punct();
+            visit_mut::visit_expr_closure_mut(self, closure);
+        }
+
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            while let Expr::Paren(syn::ExprParen { expr: inner, .. })
+            | Expr::Group(ExprGroup { expr: inner, .. }) = expr
+            {
+                *expr = mem::replace(&mut **inner, Expr::PLACEHOLDER);
+            }
+            visit_mut::visit_expr_mut(self, expr);
+            *expr = Expr::Group(ExprGroup {
+                attrs: Vec::new(),
+                group_token: token::Group::default(),
+                expr: Box::new(mem::replace(expr, Expr::PLACEHOLDER)),
+            });
+        }
+
+        fn visit_pat_mut(&mut self, pat: &mut Pat) {
+            while let Pat::Paren(PatParen { pat: inner, .. }) = pat {
+                *pat = mem::replace(&mut **inner, Pat::Verbatim(TokenStream::new()));
+            }
+            if let Pat::Or(pat) = pat {
+                pat.leading_vert = None;
+            }
+            visit_mut::visit_pat_mut(self, pat);
+        }
+
+        fn visit_type_mut(&mut self, ty: &mut Type) {
+            while let Type::Paren(TypeParen { elem: inner, .. })
+            | Type::Group(TypeGroup { elem: inner, .. }) = ty
+            {
+                *ty = mem::replace(&mut **inner, Type::Verbatim(TokenStream::new()));
+            }
+            visit_mut::visit_type_mut(self, ty);
+        }
+
+        fn visit_where_clause_mut(&mut self, where_clause: &mut WhereClause) {
+            where_clause.predicates.pop_punct();
+            visit_mut::visit_where_clause_mut(self, where_clause);
+        }
+    }
+
+    fn unwrap_block(body: &mut Expr) {
+        match body {
+            Expr::Block(ExprBlock {
+                attrs,
+                label: None,
+                block,
+            }) if attrs.is_empty() && block.stmts.len() == 1 => {
+                *body = match block.stmts.pop().unwrap() {
+                    Stmt::Expr(expr, _semi) => expr,
+                    Stmt::Macro(stmt) if stmt.semi_token.is_none() => Expr::Macro(ExprMacro {
+                        attrs: stmt.attrs,
+                        mac: stmt.mac,
+                    }),
+                    stmt => {
+                        block.stmts.push(stmt);
+                        return;
+                    }
+                };
+            }
+            Expr::Tuple(ExprTuple { attrs, elems, .. }) if attrs.is_empty() && elems.is_empty() => {
+                *body = Expr::Block(ExprBlock {
+                    attrs: Vec::new(),
+                    label: None,
+                    block: Block {
+                        brace_token: token::Brace::default(),
+                        stmts: Vec::new(),
+                    },
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let tokens = match node {
+        Node::File(node) => {
+            let mut node = node.clone();
+            Canonicalize.visit_file_mut(&mut node);
+            node.into_token_stream()
+        }
+        Node::Item(node) => {
+            let mut node = node.clone();
+            Canonicalize.visit_item_mut(&mut node);
+            node.into_token_stream()
+        }
+        Node::Stmt(node) => {
+            let mut node = node.clone();
+            Canonicalize.visit_stmt_mut(&mut node);
+            node.into_token_stream()
+        }
+        Node::Expr(node) => {
+            let mut node = node.clone();
+            Canonicalize.visit_expr_mut(&mut node);
+            node.into_token_stream()
+        }
+    };
+    events(tokens, true)
+}
+
+// Describes the innermost item, statement or expression of the syntax tree
+// whose printed text parses back as a node with different structure.
+fn semantics_mismatch(file: &File, config: &Config, formatted: &str, reparsed: &File) -> String {
+    let mut p = Printer::new(config);
+    p.source_map = Some(Recorder::default());
+    p.file(file);
+    let source_map = match p.eof_with_source_map() {
+        Ok((_output, source_map)) => source_map,
+        Err(error) => error.panic(),
+    };
+
+    let original: HashMap<(NodeKind, usize), Node> = Node::File(file)
+        .descendants()
+        .into_iter()
+        .filter_map(|node| Some(((node.kind()?, node.address()), node)))
+        .collect();
+    let lines = LineIndex::new(formatted);
+    let reparsed_nodes: Vec<(Range<usize>, Node)> = Node::File(reparsed)
+        .descendants()
+        .into_iter()
+        .map(|node| {
+            let (start, end) = node.span();
+            (lines.offset(start)..lines.offset(end), node)
+        })
+        .collect();
+
+    let differs = |mapping: &Mapping| {
+        let Some(&node) = original.get(&(mapping.kind(), mapping.node())) else {
+            return false;
+        };
+        if imports::enabled(config) && matches!(node, Node::Item(Item::Use(_))) {
+            return false;
+        }
+        let expected = canonical(node);
+        !reparsed_nodes.iter().any(|(range, other)| {
+            *range == mapping.output_range()
+                && other.kind() == node.kind()
+                && same_structure(&expected, &canonical(*other))
+        })
+    };
+
+    // Mappings are ordered by start, with every node before those nested in
+    // it, so descend into the first differing node for as long as one of its
+    // descendants differs too.
+    let mut culprit: Option<&Mapping> = None;
+    for mapping in source_map.mappings() {
+        if let Some(outer) = culprit {
+            let outer = outer.output_range();
+            let range = mapping.output_range();
+            if range.start >= outer.end {
+                break;
+            }
+            if range.end > outer.end {
+                continue;
+            }
+        }
+        if differs(mapping) {
+            culprit = Some(mapping);
+        }
+    }
+
+    let Some(mapping) = culprit else {
+        let expected = canonical(Node::File(&as_printed(file, config)));
+        let actual = canonical(Node::File(reparsed));
+        let (i, j) = match compare(&expected, &actual) {
+            Ok(loose) => loose.unwrap_or_default(),
+            Err(at) => at,
+        };
+        return format!(
+            "formatted output parses to a differently structured syntax tree\n- {}\n+ {}",
+            excerpt(&expected, i),
+            excerpt(&actual, j),
+        );
+    };
+
+    let node = original[&(mapping.kind(), mapping.node())];
+    let range = mapping.output_range();
+    let line_start = formatted[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let column = formatted[line_start..range.start].chars().count() + 1;
+    let kind = match mapping.kind() {
+        NodeKind::Item => "item",
+        NodeKind::Stmt => "statement",
+        NodeKind::Expr => "expression",
+    };
+    // The smallest node of the reparsed syntax tree that contains all of the
+    // mismatched node's output.
+    let found = reparsed_nodes
+        .iter()
+        .filter(|(other, _node)| other.start <= range.start && range.end <= other.end)
+        .min_by_key(|(other, _node)| other.len())
+        .map_or_else(String::new, |(_range, node)| render(&canonical(*node)));
+    format!(
+        "formatted output parses to a differently structured syntax tree\n\
+         --> line {}, column {}: {} printed as `{}`\n\
+         - {}\n\
+         + {}",
+        mapping.output_line(),
+        column,
+        kind,
+        &formatted[range],
+        render(&canonical(node)),
+        found,
+    )
+}
+
+// Converts line and column of a span within the formatted output to a byte
+// offset.
+struct LineIndex<'a> {
+    text: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(text: &'a str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { text, starts }
+    }
+
+    fn offset(&self, position: LineColumn) -> usize {
+        let Some(&start) = self.starts.get(position.line.wrapping_sub(1)) else {
+            return self.text.len();
+        };
+        self.text[start..]
+            .char_indices()
+            .nth(position.column)
+            .map_or(self.text.len(), |(i, _ch)| start + i)
+    }
+}
+
+#[derive(PartialEq)]
+enum Event {
+    Open(Delimiter),
+    Close(Delimiter),
+    Token(String),
+}
+
+// Flattens a token stream into a sequence of tokens and delimiters, dropping
+// differences that the printer introduces on purpose: invisible groups unless
+// `keep_invisible` is set, trailing commas other than that of a one-element
+// tuple, commas after a braced match arm, empty generic argument lists and
+// where-clauses, and bounds lists that are empty after a colon.
+//
+// With `keep_invisible` set, the sequence describes the structure of a
+// canonical syntax tree, so doc comments are left out and a one-element tuple
+// is told apart from parentheses by its nesting rather than its comma.
+fn events(tokens: TokenStream, keep_invisible: bool) -> Vec<Event> {
+    let mut events = Vec::new();
+    push_events(tokens, keep_invisible, Delimiter::None, &mut events);
+    events
+}
+
+fn push_events(
+    tokens: TokenStream,
+    keep_invisible: bool,
+    delimiter: Delimiter,
+    events: &mut Vec<Event>,
+) {
+    let mut trees = Vec::new();
+    if keep_invisible {
+        trees.extend(tokens);
+    } else {
+        flatten_invisible(tokens, &mut trees);
+    }
+
+    let is_comma =
+        |tree: &TokenTree| matches!(tree, TokenTree::Punct(punct) if punct.as_char() == ',');
+    let mut len = trees.len();
+    if len > 0 && is_comma(&trees[len - 1]) {
+        let one_element_tuple = !keep_invisible
+            && delimiter == Delimiter::Parenthesis
+            && !trees[..len - 1].iter().any(is_comma);
+        if !one_element_tuple {
+            len -= 1;
+        }
+    }
+
+    let mut i = 0;
+    while i < len {
+        if let Some((prefix, doc, consumed)) = doc_attr(&trees[i..len]) {
+            let mut lines = vec![doc];
+            i += consumed;
+            while let Some((next_prefix, doc, consumed)) = doc_attr(&trees[i..len]) {
+                if next_prefix != prefix {
+                    break;
+                }
+                lines.push(doc);
+                i += consumed;
+            }
+            if !keep_invisible {
+                push_doc_events(prefix, &lines.join("\n"), events);
+            }
+            continue;
+        }
+        let next = trees.get(i + 1).filter(|_| i + 1 < len);
+        match &trees[i] {
+            TokenTree::Group(group) => {
+                // A match arm evaluating to `()` is printed as `=> {}`.
+                let delimiter = if is_arm_body(&trees[..=i]) {
+                    Delimiter::Brace
+                } else {
+                    group.delimiter()
+                };
+                events.push(Event::Open(delimiter));
+                push_events(group.stream(), keep_invisible, delimiter, events);
+                events.push(Event::Close(delimiter));
+            }
+            TokenTree::Punct(punct) => {
+                let skip = match punct.as_char() {
+                    '<' => match next {
+                        Some(TokenTree::Punct(next)) if next.as_char() == '>' => {
+                            i += 1;
+                            true
+                        }
+                        _ => false,
+                    },
+                    ':' => {
+                        let prev_colon = i > 0
+                            && matches!(&trees[i - 1], TokenTree::Punct(prev) if prev.as_char() == ':');
+                        !prev_colon
+                            && match next {
+                                Some(TokenTree::Punct(next)) => {
+                                    matches!(next.as_char(), ',' | '>' | '=')
+                                }
+                                None => true,
+                                _ => false,
+                            }
+                    }
+                    ',' => {
+                        is_arm_body(&trees[..i])
+                            || matches!(next, Some(TokenTree::Punct(next)) if next.as_char() == '>')
+                    }
+                    _ => false,
+                };
+                if !skip {
+                    events.push(Event::Token(punct.as_char().to_string()));
+                }
+            }
+            TokenTree::Ident(ident) => {
+                let empty_where = ident == "where"
+                    && match next {
+                        Some(TokenTree::Group(group)) => group.delimiter() == Delimiter::Brace,
+                        Some(TokenTree::Punct(punct)) => punct.as_char() == ';',
+                        Some(TokenTree::Ident(_) | TokenTree::Literal(_)) => false,
+                        None => true,
+                    };
+                if !empty_where {
+                    events.push(Event::Token(ident.to_string()));
+                }
+            }
+            TokenTree::Literal(literal) => events.push(Event::Token(literal_event(literal))),
+        }
+        i += 1;
+    }
+}
+
+// Literals are compared by value because the printer may break strings with
+// line continuations, rewrite their escapes, and rewrite the digits of
+// numbers.
+fn literal_event(literal: &Literal) -> String {
+    match Lit::new(literal.clone()) {
+        Lit::Str(lit) if lit.suffix().is_empty() => format!("{:?}", lit.value()),
+        Lit::ByteStr(lit) if lit.suffix().is_empty() => format!("b{:?}", lit.value()),
+        Lit::CStr(lit) if lit.suffix().is_empty() => format!("c{:?}", lit.value()),
+        Lit::Byte(lit) if lit.suffix().is_empty() => format!("b'{}'", lit.value()),
+        Lit::Char(lit) if lit.suffix().is_empty() => format!("{:?}", lit.value()),
+        Lit::Int(lit) => format!("{}{}", lit.base10_digits(), lit.suffix()),
+        Lit::Float(lit) => format!("{}{}", lit.base10_digits(), lit.suffix()).replace('E', "e"),
+        _ => literal.to_string(),
+    }
+}
+
+// The comment prefix and text of a `#[doc = "..."]` or `#![doc = "..."]`
+// attribute at the start of `trees`, and the number of trees it spans.
+fn doc_attr(trees: &[TokenTree]) -> Option<(&'static str, String, usize)> {
+    let (prefix, group, consumed) = match trees {
+        [TokenTree::Punct(pound), TokenTree::Punct(bang), TokenTree::Group(group), ..]
+            if pound.as_char() == '#' && bang.as_char() == '!' =>
+        {
+            ("//!", group, 3)
+        }
+        [TokenTree::Punct(pound), TokenTree::Group(group), ..] if pound.as_char() == '#' => {
+            ("///", group, 2)
+        }
+        _ => return None,
+    };
+    if group.delimiter() != Delimiter::Bracket {
+        return None;
+    }
+    let mut tokens = group.stream().into_iter();
+    let mut value = match (tokens.next(), tokens.next(), tokens.next(), tokens.next()) {
+        (Some(TokenTree::Ident(ident)), Some(TokenTree::Punct(eq)), Some(value), None)
+            if ident == "doc" && eq.as_char() == '=' =>
+        {
+            value
+        }
+        _ => return None,
+    };
+    // The value is in an invisible group in the canonical form.
+    while let TokenTree::Group(group) = &value {
+        let mut tokens = group.stream().into_iter();
+        match (group.delimiter(), tokens.next(), tokens.next()) {
+            (Delimiter::None, Some(inner), None) => value = inner,
+            _ => return None,
+        }
+    }
+    match value {
+        TokenTree::Literal(literal) => match Lit::new(literal) {
+            Lit::Str(doc) => Some((prefix, doc.value(), consumed)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Doc comments are compared word by word because the printer may reflow them,
+// except for the visible lines of Rust code blocks, which are compared token
+// by token because the printer may format them.
+fn push_doc_events(prefix: &str, doc: &str, events: &mut Vec<Event>) {
+    let push_words = |line: &str, events: &mut Vec<Event>| {
+        let words = line.split_whitespace();
+        events.extend(words.map(|word| Event::Token(format!("{prefix}{word}"))));
+    };
+    let push_code = |code: &mut String, events: &mut Vec<Event>| {
+        match code.parse::<TokenStream>() {
+            Ok(tokens) => push_events(tokens, false, Delimiter::None, events),
+            Err(_) => push_words(code, events),
+        }
+        code.clear();
+    };
+
+    // The marker of the open code block, and the indentation of its fence if
+    // it is Rust code. Like rustdoc, the printer removes that indentation from
+    // the code, which matters within multi-line string literals.
+    let mut fence: Option<(&str, Option<&str>)> = None;
+    let mut code = String::new();
+    for line in doc.lines() {
+        let text = line.trim_start();
+        if let Some((marker, rust)) = fence {
+            if text.starts_with(marker) {
+                fence = None;
+                push_code(&mut code, events);
+            } else if let Some(indent) = rust.filter(|_| !attr::is_hidden_code_line(line)) {
+                code.push_str(line.strip_prefix(indent).unwrap_or(line));
+                code.push('\n');
+                continue;
+            }
+        } else if let Some(marker) = attr::code_fence(text) {
+            let rust = attr::is_rust_info(&text[marker.len()..]);
+            fence = Some((marker, rust.then(|| &line[..line.len() - text.len()])));
+        }
+        push_words(line, events);
+    }
+    push_code(&mut code, events);
+}
+
+fn flatten_invisible(tokens: TokenStream, trees: &mut Vec<TokenTree>) {
+    for tree in tokens {
+        match tree {
+            TokenTree::Group(group) if group.delimiter() == Delimiter::None => {
+                flatten_invisible(group.stream(), trees);
+            }
+            tree => trees.push(tree),
+        }
+    }
+}
+
+// Whether the tokens end with a match arm whose body is a block or unit,
+// `=> {...}` or `=> ()`, after which the comma is optional.
+fn is_arm_body(trees: &[TokenTree]) -> bool {
+    match trees {
+        [.., TokenTree::Punct(eq), TokenTree::Punct(gt), TokenTree::Group(group)] => {
+            eq.as_char() == '='
+                && gt.as_char() == '>'
+                && match group.delimiter() {
+                    Delimiter::Brace => true,
+                    Delimiter::Parenthesis => group.stream().is_empty(),
+                    Delimiter::Bracket | Delimiter::None => false,
+                }
+        }
+        _ => false,
+    }
+}
+
+// Compares two event sequences, allowing either side to contain parentheses
+// that the other does not, as the printer inserts parentheses where the syntax
+// tree's precedence requires them, and likewise braces around the body of a
+// match arm or closure and optional punctuation. Returns the index into each
+// of the first such difference, which is only acceptable if the syntax trees
+// have the same structure, or on mismatch, the index into each where it
+// occurs.
+fn compare(a: &[Event], b: &[Event]) -> Result<Option<(usize, usize)>, (usize, usize)> {
+    let mut a = Cursor::new(a);
+    let mut b = Cursor::new(b);
+    let mut loose = None;
+    loop {
+        if a.close_skipped() {
+            b.skip_arm_comma();
+            continue;
+        }
+        if b.close_skipped() {
+            a.skip_arm_comma();
+            continue;
+        }
+        match (a.peek(), b.peek()) {
+            (None, None) => return Ok(loose),
+            (Some(Event::Open(_)), Some(Event::Open(_)))
+                if a.is_macro_rules_body() && b.is_macro_rules_body() =>
+            {
+                a.stack.push(Frame::MacroRules);
+                b.stack.push(Frame::MacroRules);
+                a.pos += 1;
+                b.pos += 1;
+            }
+            (Some(Event::Open(_)), Some(Event::Open(_)))
+                if a.stack.last() == Some(&Frame::MacroRules) =>
+            {
+                a.stack.push(Frame::AnyDelimiter);
+                b.stack.push(Frame::AnyDelimiter);
+                a.pos += 1;
+                b.pos += 1;
+            }
+            (Some(Event::Close(_)), Some(Event::Close(_)))
+                if matches!(
+                    a.stack.last(),
+                    Some(Frame::MacroRules | Frame::AnyDelimiter)
+                ) =>
+            {
+                a.stack.pop();
+                b.stack.pop();
+                a.pos += 1;
+                b.pos += 1;
+            }
+            (Some(x), Some(y)) if x == y => {
+                match x {
+                    Event::Open(_) => {
+                        a.stack.push(Frame::Matched);
+                        b.stack.push(Frame::Matched);
+                    }
+                    Event::Close(_) => {
+                        a.stack.pop();
+                        b.stack.pop();
+                    }
+                    Event::Token(_) => {}
+                }
+                a.pos += 1;
+                b.pos += 1;
+            }
+            _ if a.skip_rule_separator() || b.skip_rule_separator() => {}
+            _ => {
+                let at = (a.pos, b.pos);
+                let skipped = a.skip_optional_punct()
+                    || b.skip_optional_punct()
+                    || a.skip_open(Delimiter::Brace)
+                    || b.skip_open(Delimiter::Brace)
+                    || a.skip_open(Delimiter::Parenthesis)
+                    || b.skip_open(Delimiter::Parenthesis);
+                if !skipped {
+                    return Err(at);
+                }
+                loose.get_or_insert(at);
+            }
+        }
+    }
+}
+
+// Whether two sequences produced by `canonical` are the same, other than in
+// the delimiters and separators of `macro_rules` rules.
+fn same_structure(a: &[Event], b: &[Event]) -> bool {
+    compare(a, b) == Ok(None)
+}
+
+struct Cursor<'a> {
+    events: &'a [Event],
+    pos: usize,
+    stack: Vec<Frame>,
+}
+
+// How the delimiters of an open group correspond to the other side.
+#[derive(PartialEq)]
+enum Frame {
+    Matched,
+    // Present only on this side.
+    Skipped,
+    // The body of a `macro_rules` definition, present on both sides.
+    MacroRules,
+    // A matcher or transcriber of a `macro_rules` rule, present on both sides,
+    // possibly with different delimiters.
+    AnyDelimiter,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(events: &'a [Event]) -> Self {
+        Cursor {
+            events,
+            pos: 0,
+            stack: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a Event> {
+        self.events.get(self.pos)
+    }
+
+    fn skip_open(&mut self, delimiter: Delimiter) -> bool {
+        if self.peek() != Some(&Event::Open(delimiter)) {
+            return false;
+        }
+        let skip = match delimiter {
+            Delimiter::Parenthesis => true,
+            // Body of a match arm or closure.
+            Delimiter::Brace => match &self.events[..self.pos] {
+                [.., Event::Token(eq), Event::Token(gt)] if eq == "=" && gt == ">" => true,
+                [.., Event::Token(pipe)] => pipe == "|",
+                _ => false,
+            },
+            Delimiter::Bracket | Delimiter::None => false,
+        };
+        if skip {
+            self.stack.push(Frame::Skipped);
+            self.pos += 1;
+        }
+        skip
+    }
+
+    // Whether the next group is the body of a `macro_rules` definition, whose
+    // delimiters the printer replaces with braces, as it does those of each
+    // rule's transcriber.
+    fn is_macro_rules_body(&self) -> bool {
+        match &self.events[..self.pos] {
+            [.., Event::Token(mac), Event::Token(bang), Event::Token(_name)] => {
+                mac == "macro_rules" && bang == "!"
+            }
+            _ => false,
+        }
+    }
+
+    // The semicolon after a `macro_rules` rule, which the printer adds to every
+    // rule.
+    fn skip_rule_separator(&mut self) -> bool {
+        let skip = self.stack.last() == Some(&Frame::MacroRules)
+            && matches!(self.peek(), Some(Event::Token(token)) if token == ";");
+        if skip {
+            self.pos += 1;
+        }
+        skip
+    }
+
+    // Semicolons after a block or before the end of one, the trailing comma of
+    // a where-clause, closure parameter list or parenthesized list, and the
+    // leading `|` of a match arm's pattern.
+    fn skip_optional_punct(&mut self) -> bool {
+        let next = self.events.get(self.pos + 1);
+        let skip = match self.peek() {
+            Some(Event::Token(token)) if token == ";" => {
+                let after_block = self.events[..self.pos]
+                    .iter()
+                    .rev()
+                    .find(|event| **event != Event::Close(Delimiter::None))
+                    == Some(&Event::Close(Delimiter::Brace));
+                next == Some(&Event::Close(Delimiter::Brace)) || after_block
+            }
+            Some(Event::Token(token)) if token == "," => match next {
+                Some(Event::Open(Delimiter::Brace) | Event::Close(Delimiter::Parenthesis)) => true,
+                Some(Event::Token(next)) => next == ";" || next == "|",
+                _ => false,
+            },
+            Some(Event::Token(token)) if token == "|" => match self.events[..self.pos].last() {
+                Some(Event::Open(Delimiter::Brace) | Event::Close(Delimiter::Brace)) => true,
+                Some(Event::Token(prev)) => prev == ",",
+                _ => false,
+            },
+            _ => false,
+        };
+        if skip {
+            self.pos += 1;
+        }
+        skip
+    }
+
+    fn close_skipped(&mut self) -> bool {
+        let skip = matches!(self.peek(), Some(Event::Close(_)))
+            && self.stack.last() == Some(&Frame::Skipped);
+        if skip {
+            self.stack.pop();
+            self.pos += 1;
+        }
+        skip
+    }
+
+    fn skip_arm_comma(&mut self) {
+        if let Some(Event::Token(token)) = self.peek() {
+            if token == "," {
+                self.pos += 1;
+            }
+        }
+    }
+}
+
+// Renders the events around a mismatch position.
+fn excerpt(events: &[Event], at: usize) -> String {
+    const CONTEXT: usize = 12;
+    let start = at.saturating_sub(CONTEXT);
+    let end = events.len().min(at + CONTEXT);
+    let before = render(&events[start..at.min(events.len())]);
+    let after = if at < events.len() {
+        render(&events[at..end])
+    } else {
+        "<end of input>".to_owned()
+    };
+    format!("{before} >>> {after}")
+}
+
+// Renders events as space-separated tokens. Invisible groups show as
+// parentheses, except around a single token or the entire sequence.
+fn render(events: &[Event]) -> String {
+    let is_single_token = |open: usize| {
+        matches!(events.get(open + 1), Some(Event::Token(_)))
+            && matches!(events.get(open + 2), Some(Event::Close(Delimiter::None)))
+    };
+    let mut opens = Vec::new();
+    let mut string = String::new();
+    for (i, event) in events.iter().enumerate() {
+        let text = match event {
+            Event::Open(Delimiter::Parenthesis) => "(",
+            Event::Open(Delimiter::Brace) => "{",
+            Event::Open(Delimiter::Bracket) => "[",
+            Event::Open(Delimiter::None) => {
+                opens.push(i);
+                let outermost = i == 0 && events.len() >= 2;
+                if outermost || is_single_token(i) {
+                    continue;
+                }
+                "("
+            }
+            Event::Close(Delimiter::Parenthesis) => ")",
+            Event::Close(Delimiter::Brace) => "}",
+            Event::Close(Delimiter::Bracket) => "]",
+            Event::Close(Delimiter::None) => {
+                let open = opens.pop();
+                if open == Some(0) && i == events.len() - 1 || open.is_some_and(is_single_token) {
+                    continue;
+                }
+                ")"
+            }
+            Event::Token(token) => token,
+        };
+        if !string.is_empty() {
+            string.push(' ');
+        }
+        string.push_str(text);
+    }
+    string
+}
+
+// Produces a unified diff with one hunk spanning the lines that differ.
+fn diff(expected: &str, actual: &str) -> String {
+    const CONTEXT: usize = 3;
+    let a: Vec<&str> = expected.lines().collect();
+    let b: Vec<&str> = actual.lines().collect();
+    let prefix = a.iter().zip(&b).take_while(|(a, b)| a == b).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let start = prefix.saturating_sub(CONTEXT);
+    let a_end = (a.len() - suffix + CONTEXT).min(a.len());
+    let b_end = (b.len() - suffix + CONTEXT).min(b.len());
+
+    let mut diff = format!(
+        "--- first\n+++ second\n@@ -{},{} +{},{} @@\n",
+        start + 1,
+        a_end - start,
+        start + 1,
+        b_end - start,
+    );
+    for line in &a[start..prefix] {
+        let _ = writeln!(diff, " {line}");
+    }
+    for line in &a[prefix..a.len() - suffix] {
+        let _ = writeln!(diff, "-{line}");
+    }
+    for line in &b[prefix..b.len() - suffix] {
+        let _ = writeln!(diff, "+{line}");
+    }
+    for line in &a[a.len() - suffix..a_end] {
+        let _ = writeln!(diff, " {line}");
+    }
+    diff
+}
@@ -49,3 +49,1441 @@ fn test_parenthesize_match_guard() {
         "},
     );
 }
+
+#[test]
+fn test_config_width_and_indent() {
+    let syntax_tree: syn::File = syn::parse2(quote! {
+        fn main() {
+            let _ = function_with_long_name(first_argument, second_argument, third);
+        }
+    })
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.indent_width = 2;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {"
+            fn main() {
+              let _ = function_with_long_name(first_argument, second_argument, third);
+            }
+        "},
+    );
+
+    config.max_width = 60;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {"
+            fn main() {
+              let _ = function_with_long_name(
+                first_argument,
+                second_argument,
+                third,
+              );
+            }
+        "},
+    );
+
+    let syntax_tree = syn::parse_file("const P: Point = Point { x: 1, y: 2 };").unwrap();
+    let mut config = prettyplease::Config::default();
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(pretty, "const P: Point = Point { x: 1, y: 2 };\n");
+
+    config.struct_lit_width = 16;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {"
+            const P: Point = Point {
+                x: 1,
+                y: 2,
+            };
+        "},
+    );
+}
+
+#[test]
+fn test_config_hard_tabs() {
+    let syntax_tree: syn::File = syn::parse2(quote! {
+        impl Struct {
+            fn f() {
+                if true { g(aaaaaaaaaaaaaaaaaaaa, bbbbbbbbbbbbbbbbbbbbbbbbbbbbbb, cccccccccccccccccccccccccccc) }
+            }
+        }
+    })
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.indent_style = prettyplease::IndentStyle::Tabs;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        "impl Struct {\n\
+         \tfn f() {\n\
+         \t\tif true {\n\
+         \t\t\tg(\n\
+         \t\t\t\taaaaaaaaaaaaaaaaaaaa,\n\
+         \t\t\t\tbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb,\n\
+         \t\t\t\tcccccccccccccccccccccccccccc,\n\
+         \t\t\t)\n\
+         \t\t}\n\
+         \t}\n\
+         }\n",
+    );
+}
+
+#[test]
+fn test_unparse_fragments() {
+    let expr: syn::Expr = syn::parse_quote!(match x {} - 1);
+    assert_eq!(prettyplease::unparse_expr(&expr), "match x {} - 1");
+
+    let stmt = syn::Stmt::Expr(expr, Some(syn::token::Semi::default()));
+    assert_eq!(prettyplease::unparse_stmt(&stmt), "(match x {}) - 1;\n");
+
+    let item: syn::Item = syn::parse_quote!(
+        struct S(u8, u16);
+    );
+    assert_eq!(prettyplease::unparse_item(&item), "struct S(u8, u16);\n");
+
+    let ty: syn::Type = syn::parse_quote!(Vec<&'a mut [u8]>);
+    assert_eq!(prettyplease::unparse_type(&ty), "Vec<&'a mut [u8]>");
+
+    let pat: syn::Pat = syn::parse_quote!(Some((a, ref mut b)) | None);
+    assert_eq!(
+        prettyplease::unparse_pat(&pat),
+        "Some((a, ref mut b)) | None"
+    );
+
+    let expr: syn::Expr = syn::parse_quote!(f(first_argument, second_argument));
+    let mut config = prettyplease::Config::default();
+    config.max_width = 20;
+    config.min_space = 0;
+    config.indent_width = 2;
+    assert_eq!(
+        prettyplease::unparse_expr_with(&expr, &config),
+        "f(\n  first_argument,\n  second_argument,\n)",
+    );
+    let item: syn::Item = syn::parse_quote!(
+        const N: u32 = 4294967295;
+    );
+    config.max_width = 89;
+    config.hex_bitmask_consts = true;
+    assert_eq!(
+        prettyplease::unparse_item_with(&item, &config),
+        "const N: u32 = 0xffffffff;\n",
+    );
+}
+
+#[test]
+fn test_try_unparse() {
+    let syntax_tree: syn::File = syn::parse2(quote! {
+        macro_rules! m {
+            () => {};
+            () -> {};
+        }
+    })
+    .unwrap();
+    let error = prettyplease::try_unparse(&syntax_tree).unwrap_err();
+    assert_eq!(error.kind(), "macro_rules");
+    assert_eq!(error.tokens(), "() => { } ; () -> { } ;");
+
+    let mut syntax_tree: syn::File = syn::parse2(quote! {
+        fn f() -> T {}
+    })
+    .unwrap();
+    let syn::Item::Fn(item) = &mut syntax_tree.items[0] else {
+        unreachable!();
+    };
+    item.sig.output = syn::ReturnType::Type(
+        syn::token::RArrow::default(),
+        Box::new(syn::Type::Verbatim(quote!(unknown type syntax))),
+    );
+    let error = prettyplease::try_unparse(&syntax_tree).unwrap_err();
+    assert_eq!(error.kind(), "Type::Verbatim");
+    assert_eq!(
+        error.to_string(),
+        "unsupported Type::Verbatim `unknown type syntax`",
+    );
+}
+
+#[test]
+fn test_raw_token_fallback() {
+    let mut syntax_tree: syn::File = syn::parse2(quote! {
+        fn f() -> T {}
+        macro_rules! m {
+            () => {};
+            ($e:expr) -> { $e };
+        }
+    })
+    .unwrap();
+    let syn::Item::Fn(item) = &mut syntax_tree.items[0] else {
+        unreachable!();
+    };
+    item.sig.output = syn::ReturnType::Type(
+        syn::token::RArrow::default(),
+        Box::new(syn::Type::Verbatim(quote!(unknown type syntax))),
+    );
+    syntax_tree.items.push(syn::Item::Verbatim(quote!(x y z;)));
+
+    let mut config = prettyplease::Config::default();
+    config.raw_token_fallback = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {"
+            fn f() -> unknown type syntax {}
+            macro_rules! m {
+                () => {};
+                ($e:expr) -> { $e };
+            }
+            x y z;
+        "},
+    );
+}
+
+#[cfg(feature = "verbatim")]
+#[test]
+fn test_registered_macros() {
+    use prettyplease::MacroArgs;
+
+    let syntax_tree: syn::File = syn::parse2(quote! {
+        handlers! {
+            fn a() {} struct B;
+        }
+        fn main() -> anyhow::Result<()> {
+            let v = serde_json::json![1+2, f(x)];
+            within! { let x = 1; x*2 }
+            if failed { anyhow::bail!("failed after {} attempts: {}", attempts, describe_error(&last_error)); }
+            unknown!(1+2)
+        }
+    })
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.macros.insert("app::handlers", MacroArgs::Items);
+    config.macros.insert("serde_json::json", MacroArgs::Exprs);
+    config.macros.insert("within", MacroArgs::Block);
+    config.macros.insert("anyhow::bail", MacroArgs::FormatArgs);
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {r#"
+            handlers! {
+                fn a() {}
+                struct B;
+            }
+            fn main() -> anyhow::Result<()> {
+                let v = serde_json::json![1 + 2, f(x)];
+                within! {
+                    let x = 1;
+                    x * 2
+                }
+                if failed {
+                    anyhow::bail!(
+                        "failed after {} attempts: {}",
+                        attempts,
+                        describe_error(&last_error),
+                    );
+                }
+                unknown!(1 + 2)
+            }
+        "#},
+    );
+}
+
+#[cfg(feature = "verbatim")]
+#[test]
+fn test_unknown_macros() {
+    let syntax_tree: syn::File = syn::parse2(quote! {
+        fn main() {
+            let _ = my_macro!(first_argument.method_call(), second_argument + third_argument * 2, "last argument");
+            with_stmts! { let x = 1; x*2 }
+            ensure!(matches!(x, | 1 | 2));
+            opaque!(a => b);
+            let _ = try_opt!(foo(bar, baz,));
+            let _ = smallvec![0u8; 32];
+            m!(a; b);
+            m!(struct A;);
+            m![fn f() {}];
+        }
+    })
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.format_unknown_macros = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {r#"
+            fn main() {
+                let _ = my_macro!(
+                    first_argument.method_call(),
+                    second_argument + third_argument * 2,
+                    "last argument"
+                );
+                with_stmts! {
+                    let x = 1;
+                    x * 2
+                }
+                ensure!(matches!(x, | 1 | 2));
+                opaque!(a => b);
+                let _ = try_opt!(foo(bar, baz,));
+                let _ = smallvec![0u8; 32];
+                m!(a; b);
+                m!(struct A;);
+                m![fn f() {}];
+            }
+        "#},
+    );
+
+    // Each level of nesting must not multiply the work of deciding how to
+    // format the levels inside it.
+    let depth = 18;
+    let source = format!(
+        "fn main() {{ {}x{}; }}",
+        "m!(".repeat(depth),
+        ", 1)".repeat(depth),
+    );
+    let syntax_tree = syn::parse_file(&source).unwrap();
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    let reparsed = syn::parse_file(&pretty).unwrap();
+    assert_eq!(
+        quote!(#reparsed).to_string(),
+        quote!(#syntax_tree).to_string(),
+    );
+    assert!(pretty.contains("m!(x, 1)"), "{pretty}");
+}
+
+#[cfg(feature = "verbatim")]
+#[test]
+fn test_format_macro_rules() {
+    let syntax_tree: syn::File = syn::parse2(quote! {
+        macro_rules! impl_name {
+            ($($t:ty),*) => { $(impl Name for $t { fn name() -> &'static str { stringify!($t) } })* };
+        }
+        macro_rules! call {
+            ($f:ident, $($arg:expr),*) => { $crate::helpers::$f($($arg),*) };
+        }
+        macro_rules! arms {
+            ($($p:pat => $e:expr),*) => { match x { $($p => $e,)* } };
+        }
+    })
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.format_macro_rules = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {"
+            macro_rules! impl_name {
+                ($($t:ty),*) => {
+                    $(
+                        impl Name for $t {
+                            fn name() -> &'static str {
+                                stringify!($t)
+                            }
+                        }
+                    )*
+                };
+            }
+            macro_rules! call {
+                ($f:ident, $($arg:expr),*) => {
+                    $crate::helpers::$f($($arg),*)
+                };
+            }
+            macro_rules! arms {
+                ($($p:pat => $e:expr),*) => {
+                    match x { $($p => $e,)* }
+                };
+            }
+        "},
+    );
+}
+
+#[cfg(feature = "verbatim")]
+#[test]
+fn test_macro2() {
+    let syntax_tree = syn::parse_file(indoc! {"
+        pub macro single($x:expr, $y:expr) { $x + $y }
+        pub(crate) macro multi { ($x:expr) => { $x * 2 }, ($x:expr, $($rest:expr),+) => { $x + multi!($($rest),+) } }
+        macro other { anything else }
+    "})
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.format_macro_rules = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {"
+            pub macro single($x:expr, $y:expr) {
+                $x + $y
+            }
+            pub(crate) macro multi {
+                ($x:expr) => {
+                    $x * 2
+                },
+                ($x:expr, $($rest:expr),+) => {
+                    $x + multi!($($rest),+)
+                },
+            }
+            macro other {
+                anything else
+            }
+        "},
+    );
+}
+
+#[test]
+fn test_wrap_doc_comments() {
+    let syntax_tree = syn::parse_file(indoc! {"
+        mod pets {
+            /// The status of a pet in the store. This description comes from an OpenAPI document
+            /// and was written as one long line, with - dashes - that must not start a line.
+            ///
+            /// ```
+            /// let status = Status::from_str(\"available\").expect(\"a known status name to parse\");
+            /// ```
+            ///
+            /// - A list item that is left alone even though it is longer than the maximum width.
+            ///
+            /// [OpenAPI]: https://spec.openapis.org/oas/latest.html#schema-object-with-a-long-anchor
+            ///
+            /// A hard line break ends this line.\x20\x20
+            /// So does a trailing\\
+            /// backslash.
+            pub enum Status {}
+        }
+    "})
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.max_width = 80;
+    config.wrap_doc_comments = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {"
+            mod pets {
+                /// The status of a pet in the store. This description comes from an OpenAPI
+                /// document and was written as one long line, with - dashes - that must not
+                /// start a line.
+                ///
+                /// ```
+                /// let status = Status::from_str(\"available\").expect(\"a known status name to parse\");
+                /// ```
+                ///
+                /// - A list item that is left alone even though it is longer than the maximum width.
+                ///
+                /// [OpenAPI]: https://spec.openapis.org/oas/latest.html#schema-object-with-a-long-anchor
+                ///
+                /// A hard line break ends this line.
+                /// So does a trailing\\
+                /// backslash.
+                pub enum Status {}
+            }
+        "},
+    );
+}
+
+#[cfg(feature = "verbatim")]
+#[test]
+fn test_format_doc_code_blocks() {
+    let syntax_tree = syn::parse_file(indoc! {r#"
+        mod client {
+            #[doc = " Sends the request."]
+            #[doc = ""]
+            #[doc = " ```rust"]
+            #[doc = " # use api::Client;"]
+            #[doc = " # async fn run(client: Client, request_id: &str) -> api::Result<()> {"]
+            #[doc = " let pet = client.get_pet_by_id().pet_id(42).header(\"x-request-id\", request_id).send().await?; println!(\"{}\", pet.name);"]
+            #[doc = " # Ok(())"]
+            #[doc = " # }"]
+            #[doc = " ```"]
+            #[doc = ""]
+            #[doc = " ```text"]
+            #[doc = " GET /pet/{petId}"]
+            #[doc = " ```"]
+            pub fn send() {}
+        }
+    "#})
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.max_width = 80;
+    config.format_doc_code_blocks = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {r#"
+            mod client {
+                /// Sends the request.
+                ///
+                /// ```rust
+                /// # use api::Client;
+                /// # async fn run(client: Client, request_id: &str) -> api::Result<()> {
+                /// let pet = client
+                ///     .get_pet_by_id()
+                ///     .pet_id(42)
+                ///     .header("x-request-id", request_id)
+                ///     .send()
+                ///     .await?;
+                /// println!("{}", pet.name);
+                /// # Ok(())
+                /// # }
+                /// ```
+                ///
+                /// ```text
+                /// GET /pet/{petId}
+                /// ```
+                pub fn send() {}
+            }
+        "#},
+    );
+
+    let syntax_tree = syn::parse_file(indoc! {r#"
+        use std::io; use std::fmt;
+        #[doc = " ```"]
+        #[doc = " use std::io; use std::fmt;"]
+        #[doc = " ```"]
+        pub fn write() {}
+    "#})
+    .unwrap();
+
+    config.reorder_imports = true;
+    config.imports_granularity = prettyplease::ImportsGranularity::Crate;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {"
+            use std::{fmt, io};
+            /// ```
+            /// use std::io;
+            /// use std::fmt;
+            /// ```
+            pub fn write() {}
+        "},
+    );
+}
+
+#[test]
+fn test_wrap_string_literals() {
+    let syntax_tree = syn::parse_file(indoc! {r#"
+        fn main() {
+            let description = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua.";
+            let short = "already \
+                         continued";
+            let raw = r"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor";
+        }
+    "#})
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.max_width = 80;
+    config.wrap_string_literals = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {r#"
+            fn main() {
+                let description = "Lorem ipsum dolor sit amet, consectetur adipiscing \
+                    elit, sed do eiusmod tempor incididunt ut labore et dolore magna \
+                    aliqua.";
+                let short = "already continued";
+                let raw = r"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor";
+            }
+        "#},
+    );
+}
+
+#[test]
+fn test_numeric_literals() {
+    let syntax_tree = syn::parse_file(indoc! {"
+        pub const UINT_MAX: u32 = 4294967295u32;
+        pub const MASK: u32 = 65280;
+        pub const LIMIT: u64 = 1000000;
+        fn f() {
+            let a = 0xdeadbeef_u32;
+            let b = 1.0E10f64;
+            let c = 1_2_3;
+        }
+    "})
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.digit_grouping = Some(5);
+    config.literal_case = prettyplease::LiteralCase::Upper;
+    config.literal_suffix = prettyplease::LiteralSuffix::Attached;
+    config.hex_bitmask_consts = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {"
+            pub const UINT_MAX: u32 = 0xFFFF_FFFFu32;
+            pub const MASK: u32 = 0xFF00;
+            pub const LIMIT: u64 = 1_000_000;
+            fn f() {
+                let a = 0xDEAD_BEEFu32;
+                let b = 1.0e10f64;
+                let c = 123;
+            }
+        "},
+    );
+
+    let config = prettyplease::Config::default();
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {"
+            pub const UINT_MAX: u32 = 4294967295u32;
+            pub const MASK: u32 = 65280;
+            pub const LIMIT: u64 = 1000000;
+            fn f() {
+                let a = 0xdeadbeef_u32;
+                let b = 1.0E10f64;
+                let c = 1_2_3;
+            }
+        "},
+    );
+
+    let syntax_tree = syn::parse_file(indoc! {"
+        const ZERO: u8 = 0;
+        const PAGE_SIZE: usize = 4096;
+        const MAX: u128 = 340282366920938463463374607431768211455;
+    "})
+    .unwrap();
+    let mut config = prettyplease::Config::default();
+    config.hex_bitmask_consts = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {"
+            const ZERO: u8 = 0;
+            const PAGE_SIZE: usize = 4096;
+            const MAX: u128 = 0xffffffffffffffffffffffffffffffff;
+        "},
+    );
+}
+
+#[test]
+fn test_literal_escapes() {
+    let syntax_tree = syn::parse_file(indoc! {r#"
+        fn f() {
+            let a = "caf\u{e9} \"x\"";
+            let b = "C:\\path\\to\\file";
+            let c = b"\x41\xff";
+            let d = '\u{2764}';
+        }
+    "#})
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.literal_escapes = prettyplease::LiteralEscapes::Unicode;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {r#"
+            fn f() {
+                let a = "café \"x\"";
+                let b = r"C:\path\to\file";
+                let c = b"A\xff";
+                let d = '❤';
+            }
+        "#},
+    );
+
+    config.literal_escapes = prettyplease::LiteralEscapes::Ascii;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {r#"
+            fn f() {
+                let a = "caf\u{e9} \"x\"";
+                let b = r"C:\path\to\file";
+                let c = b"A\xff";
+                let d = '\u{2764}';
+            }
+        "#},
+    );
+}
+
+#[test]
+fn test_meta_lists() {
+    let syntax_tree = syn::parse_file(indoc! {r#"
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+        #[cfg_attr(feature = "serde", serde(rename_all = "camelCase", deny_unknown_fields, default))]
+        #[cfg(all(any(target_os = "linux", target_os = "android"), not(target_arch = "wasm32"), unix))]
+        #[repr(C, align(8))]
+        struct S;
+    "#})
+    .unwrap();
+
+    let mut config = prettyplease::Config::default();
+    config.wrap_attribute_args = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(
+        pretty,
+        indoc! {r#"
+            #[derive(
+                Debug,
+                Clone,
+                Copy,
+                PartialEq,
+                Eq,
+                PartialOrd,
+                Ord,
+                Hash,
+                Default,
+                Serialize,
+                Deserialize,
+            )]
+            #[cfg_attr(
+                feature = "serde",
+                serde(rename_all = "camelCase", deny_unknown_fields, default),
+            )]
+            #[cfg(all(
+                any(target_os = "linux", target_os = "android"),
+                not(target_arch = "wasm32"),
+                unix,
+            ))]
+            #[repr(C, align(8))]
+            struct S;
+        "#},
+    );
+}
+
+#[test]
+fn test_layout_doc() {
+    use prettyplease::layout::Doc;
+
+    let layout = |words: &[&'static str]| {
+        let mut doc = Doc::new(20, 0);
+        doc.word("[");
+        doc.cbox(2);
+        doc.zerobreak();
+        for (i, word) in words.iter().enumerate() {
+            doc.ibox(0);
+            doc.word(*word);
+            doc.end();
+            doc.trailing_comma(i + 1 == words.len());
+        }
+        doc.offset(-2);
+        doc.end();
+        doc.word("]");
+        doc.hardbreak();
+        doc.finish()
+    };
+
+    assert_eq!(layout(&["a", "b", "c"]), "[a, b, c]\n");
+    assert_eq!(
+        layout(&["first", "second", "third"]),
+        "[\n  first,\n  second,\n  third,\n]\n",
+    );
+    assert_eq!(layout(&[]), "[]\n");
+
+    // Only the `\` continuing a string literal needs room before the margin.
+    // Other breaks with a pre-break character, such as a trailing comma, lay
+    // out the line without it.
+    let mut doc = Doc::new(10, 0);
+    doc.word("f(");
+    doc.ibox(4);
+    doc.zerobreak();
+    doc.word("aaa");
+    doc.trailing_comma(false);
+    doc.word("bbb");
+    doc.trailing_comma(true);
+    doc.offset(-4);
+    doc.end();
+    doc.word(")");
+    assert_eq!(doc.finish(), "f(aaa, bbb,\n)");
+
+    let mut doc = Doc::with_tabs(20, 0, 4);
+    doc.word("{");
+    doc.cbox(6);
+    doc.hardbreak();
+    doc.word("x");
+    doc.hardbreak();
+    doc.offset(-6);
+    doc.end();
+    doc.word("}");
+    assert_eq!(doc.finish(), "{\n\t  x\n}");
+}
+
+#[test]
+#[should_panic = "unclosed box"]
+fn test_layout_doc_unclosed_box() {
+    let mut doc = prettyplease::layout::Doc::new(100, 60);
+    doc.cbox(4);
+    doc.word("x");
+    doc.finish();
+}
+
+#[test]
+#[should_panic = "without an open box"]
+fn test_layout_doc_unbalanced_end() {
+    let mut doc = prettyplease::layout::Doc::new(100, 60);
+    doc.word("x");
+    doc.end();
+}
+
+#[test]
+#[should_panic = "would indent by -8 columns"]
+fn test_layout_doc_negative_indent() {
+    let mut doc = prettyplease::layout::Doc::new(100, 60);
+    doc.cbox(4);
+    doc.cbox(-12);
+}
+
+#[test]
+fn test_unparse_to_writer() {
+    let functions = (0..500u32).map(|i| {
+        let name = quote::format_ident!("function_{i}");
+        quote! {
+            pub fn #name(argument: u32) -> u32 {
+                if argument > #i { helper(argument, #i).unwrap_or_default() } else { 0 }
+            }
+        }
+    });
+    let syntax_tree: syn::File = syn::parse2(quote!(#(#functions)*)).unwrap();
+    let expected = prettyplease::unparse(&syntax_tree);
+
+    let mut bytes = Vec::new();
+    prettyplease::unparse_to_writer(&syntax_tree, &mut bytes).unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), expected);
+
+    let mut string = String::new();
+    prettyplease::unparse_to_fmt_writer(&syntax_tree, &mut string).unwrap();
+    assert_eq!(string, expected);
+
+    let mut syntax_tree = syntax_tree;
+    let config = prettyplease::Config::default();
+    let position = syntax_tree.items.len() / 2;
+    let item = syn::Item::Verbatim(quote!(not an item));
+    syntax_tree.items.insert(position, item);
+
+    let mut bytes = Vec::new();
+    let error =
+        prettyplease::unparse_to_writer_with(&syntax_tree, &config, &mut bytes).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let error = error
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<prettyplease::Error>()
+        .unwrap();
+    assert_eq!(error.tokens(), "not an item");
+    let written = String::from_utf8(bytes).unwrap();
+    assert!(!written.is_empty());
+    assert!(expected.starts_with(&written));
+    assert!(!written.contains(&format!("function_{position}(")));
+
+    let mut bytes = Vec::new();
+    let error = prettyplease::unparse_to_writer(&syntax_tree, &mut bytes).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(String::from_utf8(bytes).unwrap(), written);
+
+    let mut string = String::new();
+    let result = prettyplease::unparse_to_fmt_writer_with(&syntax_tree, &config, &mut string);
+    assert_eq!(result.unwrap_err().kind(), "Item::Verbatim");
+    assert_eq!(string, written);
+}
+
+#[cfg(feature = "span-locations")]
+#[test]
+fn test_unparse_with_comments() {
+    let source = indoc! {r#"
+        // Header.
+        struct S {
+            a: u8, // after a
+            /* before b */ b: u8,
+        }
+
+        fn f(x: u8) -> u8 {
+            let s = "// not a comment";
+            match x {
+                // zero
+                0 => 1,
+                _ => 2,
+            }
+            // trailing
+        }
+
+        macro_rules! m {
+            // first rule
+            () => {};
+            // last rule
+            ($e:expr) => { $e };
+            // end of rules
+        }
+        fn g() {}
+    "#};
+    let syntax_tree = syn::parse_file(source).unwrap();
+    let config = prettyplease::Config::default();
+    let pretty = prettyplease::unparse_with_comments(&syntax_tree, source, &config);
+    let expected = indoc! {r#"
+        // Header.
+        struct S {
+            a: u8,
+            // after a
+            /* before b */
+            b: u8,
+        }
+        fn f(x: u8) -> u8 {
+            let s = "// not a comment";
+            match x {
+                // zero
+                0 => 1,
+                _ => 2,
+            }
+            // trailing
+        }
+        macro_rules! m {
+            // first rule
+            () => {};
+            // last rule
+            ($e:expr) => {
+                $e
+            };
+            // end of rules
+        }
+        fn g() {}
+    "#};
+    assert_eq!(pretty, expected);
+}
+
+#[cfg(feature = "span-locations")]
+#[test]
+fn test_comments_in_expressions() {
+    let source = indoc! {"
+        fn f(a: u8 /* a */, b: u8) {
+            let v = unsafe {
+                // SAFETY: fine
+                ptr.read()
+            };
+            let g = |x| {
+                // closure
+                x + 1
+            };
+            if a {
+                // then
+                b()
+            } else {
+                // else
+                c()
+            }
+            let s = S {
+                // field
+                a: 1,
+                b: 2, // after b
+            };
+            let arr = [
+                1, // one
+                2,
+            ];
+            call(
+                // argument
+                a,
+            );
+        }
+    "};
+    let syntax_tree = syn::parse_file(source).unwrap();
+    let config = prettyplease::Config::default();
+    let pretty = prettyplease::unparse_with_comments(&syntax_tree, source, &config);
+    let expected = indoc! {"
+        fn f(
+            a: u8,
+            /* a */
+            b: u8,
+        ) {
+            let v = unsafe {
+                // SAFETY: fine
+                ptr.read()
+            };
+            let g = |x| {
+                // closure
+                x + 1
+            };
+            if a {
+                // then
+                b()
+            } else {
+                // else
+                c()
+            }
+            let s = S {
+                // field
+                a: 1,
+                b: 2,
+                // after b
+            };
+            let arr = [
+                1,
+                // one
+                2,
+            ];
+            call(
+                // argument
+                a,
+            );
+        }
+    "};
+    assert_eq!(pretty, expected);
+}
+
+#[cfg(feature = "span-locations")]
+#[test]
+fn test_preserve_blank_lines() {
+    let source = indoc! {"
+        use std::fmt;
+
+
+        struct S;
+        fn f() {
+
+            let a = 1;
+
+            let b = 2;
+            let c = 3;
+        }
+    "};
+    let syntax_tree = syn::parse_file(source).unwrap();
+    let mut config = prettyplease::Config::default();
+    config.preserve_blank_lines = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    let expected = indoc! {"
+        use std::fmt;
+
+        struct S;
+        fn f() {
+            let a = 1;
+
+            let b = 2;
+            let c = 3;
+        }
+    "};
+    assert_eq!(pretty, expected);
+}
+
+#[test]
+fn test_imports_normalization() {
+    let syntax_tree: syn::File = syn::parse_quote! {
+        use std::io::{Write, self};
+        use crate::b;
+        use std::fmt;
+        use a10;
+        use a9;
+        use std::fmt;
+        pub use std::collections::HashMap;
+        use std::collections::{HashSet, BTreeMap};
+        fn f() {}
+    };
+
+    let mut config = prettyplease::Config::default();
+    config.reorder_imports = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    let expected = indoc! {"
+        use crate::b;
+        use a9;
+        use a10;
+        pub use std::collections::HashMap;
+        use std::collections::{BTreeMap, HashSet};
+        use std::fmt;
+        use std::io::{self, Write};
+        fn f() {}
+    "};
+    assert_eq!(pretty, expected);
+
+    config.imports_granularity = prettyplease::ImportsGranularity::Module;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    assert_eq!(pretty, expected);
+
+    config.imports_granularity = prettyplease::ImportsGranularity::Crate;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    let expected = indoc! {"
+        use crate::b;
+        use a9;
+        use a10;
+        pub use std::collections::HashMap;
+        use std::{
+            collections::{BTreeMap, HashSet},
+            fmt, io::{self, Write},
+        };
+        fn f() {}
+    "};
+    assert_eq!(pretty, expected);
+
+    let syntax_tree: syn::File = syn::parse_quote! {
+        use a::{self};
+        use b::c::{self as x};
+    };
+    let expected = indoc! {"
+        use a::{self};
+        use b::c::{self as x};
+    "};
+    for granularity in [
+        prettyplease::ImportsGranularity::Module,
+        prettyplease::ImportsGranularity::Crate,
+    ] {
+        config.imports_granularity = granularity;
+        let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+        assert_eq!(pretty, expected);
+    }
+
+    let syntax_tree: syn::File = syn::parse_quote! {
+        pub use a;
+        use a;
+        pub use a;
+        fn f() {
+            use c::{y, x};
+            use b;
+            let _ = x;
+        }
+    };
+    let mut config = prettyplease::Config::default();
+    config.reorder_imports = true;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    let expected = indoc! {"
+        pub use a;
+        use a;
+        fn f() {
+            use b;
+            use c::{x, y};
+            let _ = x;
+        }
+    "};
+    assert_eq!(pretty, expected);
+
+    let syntax_tree: syn::File = syn::parse_quote! {
+        #[cfg(test)]
+        use c::{y, x};
+    };
+    config.reorder_imports = false;
+    config.imports_granularity = prettyplease::ImportsGranularity::Module;
+    let pretty = prettyplease::unparse_with(&syntax_tree, &config);
+    let expected = indoc! {"
+        #[cfg(test)]
+        use c::{y, x};
+    "};
+    assert_eq!(pretty, expected);
+}
+
+#[cfg(feature = "span-locations")]
+#[test]
+fn test_unparse_with_source_map() {
+    use prettyplease::NodeKind::{Expr, Item, Stmt};
+
+    let source = "fn f() { let x = g(1,\n2); x }";
+    let syntax_tree = syn::parse_file(source).unwrap();
+    let config = prettyplease::Config::default();
+    let (pretty, source_map) = prettyplease::unparse_with_source_map(&syntax_tree, &config);
+    let expected = indoc! {"
+        fn f() {
+            let x = g(1, 2);
+            x
+        }
+    "};
+    assert_eq!(pretty, expected);
+
+    let mappings: Vec<_> = source_map
+        .mappings()
+        .iter()
+        .map(|mapping| {
+            (
+                mapping.kind(),
+                &pretty[mapping.output_range()],
+                mapping.output_line(),
+                mapping.span().start().line,
+            )
+        })
+        .collect();
+    assert_eq!(
+        mappings,
+        [
+            (Item, expected.trim_end(), 1, 1),
+            (Stmt, "let x = g(1, 2);", 2, 1),
+            (Expr, "g(1, 2)", 2, 1),
+            (Expr, "g", 2, 1),
+            (Expr, "1", 2, 1),
+            (Expr, "2", 2, 2),
+            (Stmt, "x", 3, 2),
+            (Expr, "x", 3, 2),
+        ],
+    );
+
+    // Method chains in statement position are printed by a separate path.
+    let syntax_tree = syn::parse_file("fn f() { foo.bar(1).baz(2); }").unwrap();
+    let (pretty, source_map) = prettyplease::unparse_with_source_map(&syntax_tree, &config);
+    let exprs: Vec<_> = source_map
+        .mappings()
+        .iter()
+        .filter(|mapping| mapping.kind() == Expr)
+        .map(|mapping| &pretty[mapping.output_range()])
+        .collect();
+    assert_eq!(exprs, ["foo.bar(1).baz(2)", "foo.bar(1)", "foo", "1", "2"]);
+}
+
+#[test]
+fn test_write_if_changed() {
+    let syntax_tree: syn::File = syn::parse_quote! {
+        fn main() {}
+    };
+    let dir = std::env::temp_dir().join(format!("prettyplease-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("generated.rs");
+
+    let mut config = prettyplease::Config::default();
+    config.header = Some("// @generated".to_owned());
+    assert!(prettyplease::write_if_changed_with(&path, &syntax_tree, &config).unwrap());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "// @generated\nfn main() {}\n",
+    );
+    assert!(!prettyplease::write_if_changed_with(&path, &syntax_tree, &config).unwrap());
+    assert!(prettyplease::write_if_changed(&path, &syntax_tree).unwrap());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn main() {}\n");
+
+    std::thread::scope(|scope| {
+        for i in 0..8 {
+            let path = &path;
+            let mut config = prettyplease::Config::default();
+            config.header = Some(format!("// thread {i}"));
+            scope.spawn(move || {
+                let syntax_tree: syn::File = syn::parse_quote! {
+                    fn main() {}
+                };
+                for _ in 0..20 {
+                    prettyplease::write_if_changed_with(path, &syntax_tree, &config).unwrap();
+                }
+            });
+        }
+    });
+    let written = std::fs::read_to_string(&path).unwrap();
+    assert!(written.starts_with("// thread "), "{written}");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli() {
+    use std::io::Write as _;
+    use std::process::{Command, Stdio};
+
+    let run = |args: &[&std::ffi::OsStr], stdin: &str| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_prettyplease"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+
+    let source = "// leading\nfn  main( ) {\n// body\nf(1); }\n";
+    let formatted = indoc! {"
+        // leading
+        fn main() {
+            // body
+            f(1);
+        }
+    "};
+
+    let (status, stdout, stderr) = run(&[], source);
+    assert_eq!(
+        (status, stdout.as_str(), stderr.as_str()),
+        (Some(0), formatted, "")
+    );
+
+    let (status, _, stderr) = run(&["--check".as_ref()], source);
+    assert_eq!(
+        (status, stderr.as_str()),
+        (Some(1), "<stdin> is not formatted\n")
+    );
+    let (status, _, _) = run(&["--check".as_ref()], formatted);
+    assert_eq!(status, Some(0));
+
+    let (status, _, stderr) = run(&[], "fn main() {");
+    assert_eq!(status, Some(1));
+    assert!(stderr.starts_with("error: <stdin>:1:"), "{stderr}");
+
+    let (status, _, _) = run(&["--bogus".as_ref()], "");
+    assert_eq!(status, Some(2));
+
+    let dir = std::env::temp_dir().join(format!("prettyplease-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.rs");
+    std::fs::write(&path, source).unwrap();
+    let (status, stdout, stderr) = run(&["-w".as_ref(), path.as_ref()], "");
+    assert_eq!(
+        (status, stdout.as_str(), stderr.as_str()),
+        (Some(0), "", "")
+    );
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), formatted);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "verify")]
+#[test]
+fn test_verify() {
+    let mut reorder_imports = prettyplease::Config::default();
+    reorder_imports.reorder_imports = true;
+    reorder_imports.imports_granularity = prettyplease::ImportsGranularity::Crate;
+    let configs = [prettyplease::Config::default(), reorder_imports];
+
+    let src = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    for entry in std::fs::read_dir(src).unwrap() {
+        let path = entry.unwrap().path();
+        let source = std::fs::read_to_string(&path).unwrap();
+        let syntax_tree = syn::parse_file(&source).unwrap();
+        for config in &configs {
+            if let Err(error) = prettyplease::verify_semantics(&syntax_tree, config) {
+                panic!("{}: {}", path.display(), error);
+            }
+        }
+    }
+
+    let syntax_tree: syn::File = syn::parse_quote! {
+        compile_error! {
+            "unsupported target"
+        }
+        fn main() {}
+    };
+    let formatted = prettyplease::verify(&syntax_tree, &configs[0]).unwrap();
+    assert_eq!(formatted, prettyplease::unparse(&syntax_tree));
+
+    let mut config = prettyplease::Config::default();
+    config.raw_token_fallback = true;
+    let syntax_tree = syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![syn::Item::Verbatim(quote!(struct;))],
+    };
+    let error = prettyplease::verify(&syntax_tree, &config).unwrap_err();
+    assert_eq!(error.kind(), prettyplease::VerifyErrorKind::Reparse);
+}
+
+#[cfg(feature = "verbatim")]
+#[test]
+fn test_brace_delimited_std_macros() {
+    let syntax_tree = syn::parse_file(indoc! {r#"
+        compile_error! {
+            "unsupported target"
+        }
+        fn main() {
+            println! {"{}", 1}
+            let v = vec! {1, 2};
+        }
+    "#})
+    .unwrap();
+
+    let pretty = prettyplease::unparse(&syntax_tree);
+    assert_eq!(
+        pretty,
+        indoc! {r#"
+            compile_error! {
+                "unsupported target"
+            }
+            fn main() {
+                println! {
+                    "{}", 1
+                }
+                let v = vec![1, 2];
+            }
+        "#},
+    );
+}
+
+#[cfg(feature = "verify")]
+#[test]
+fn test_verify_semantics() {
+    use syn::visit_mut::{self, VisitMut};
+
+    // Stands in for a printer bug that drops the parentheses around `b + c`.
+    struct ReplaceZ;
+
+    impl VisitMut for ReplaceZ {
+        fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+            if let syn::Expr::Path(path) = expr {
+                if path.path.is_ident("z") {
+                    *expr = syn::Expr::Verbatim(quote!(b + c));
+                    return;
+                }
+            }
+            visit_mut::visit_expr_mut(self, expr);
+        }
+    }
+
+    let mut syntax_tree: syn::File = syn::parse_quote! {
+        fn main() {
+            let x = 1;
+            let y = f(a * z, d);
+        }
+    };
+    ReplaceZ.visit_file_mut(&mut syntax_tree);
+
+    let mut config = prettyplease::Config::default();
+    config.raw_token_fallback = true;
+    prettyplease::verify(&syntax_tree, &config).unwrap();
+    let error = prettyplease::verify_semantics(&syntax_tree, &config).unwrap_err();
+    assert_eq!(error.kind(), prettyplease::VerifyErrorKind::Semantics);
+    assert_eq!(
+        error.to_string(),
+        indoc! {"
+            formatted output parses to a differently structured syntax tree
+            --> line 3, column 19: expression printed as `b + c`
+            - b + c
+            + ( a * b ) + c"
+        },
+    );
+}
+
+#[cfg(feature = "verify")]
+#[test]
+fn test_verify_tuple_parens() {
+    // The printer leaves out the comma of a one-element tuple pattern that has
+    // none in the syntax tree, so the output parses as a parenthesized pattern.
+    let mut syntax_tree: syn::File = syn::parse_quote! {
+        fn main() {
+            let (a,) = x;
+        }
+    };
+    let syn::Item::Fn(item) = &mut syntax_tree.items[0] else {
+        unreachable!();
+    };
+    let syn::Stmt::Local(local) = &mut item.block.stmts[0] else {
+        unreachable!();
+    };
+    let syn::Pat::Tuple(pat) = &mut local.pat else {
+        unreachable!();
+    };
+    pat.elems.pop_punct();
+
+    let config = prettyplease::Config::default();
+    let error = prettyplease::verify(&syntax_tree, &config).unwrap_err();
+    assert_eq!(error.kind(), prettyplease::VerifyErrorKind::Tokens);
+    assert_eq!(
+        error.to_string(),
+        indoc! {"
+            formatted output has different tokens than the syntax tree
+            - fn main ( ) { let ( a >>> , ) = x ; }
+            + fn main ( ) { let ( a >>> ) = x ; }"
+        },
+    );
+    let error = prettyplease::verify_semantics(&syntax_tree, &config).unwrap_err();
+    assert_eq!(error.kind(), prettyplease::VerifyErrorKind::Semantics);
+}